@@ -10,16 +10,87 @@ struct ConnectionWithSchema<T: JsonSchema> {
     pub page_info: PageInfo,
 }
 
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+struct EdgeConnectionWithSchema<T: JsonSchema> {
+    pub edges: Vec<Edge<T>>,
+    pub page_info: PageInfo,
+}
+
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+struct EdgeWithSchema<T: JsonSchema> {
+    pub node: T,
+    pub cursor: Cursor,
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct Connection<T> {
     pub nodes: Vec<T>,
     pub page_info: PageInfo,
 }
 
-#[derive(Clone, Debug, Serialize, JsonSchema)]
+/// A page of results where every node is paired with its own cursor, so that pagination
+/// can resume from any of them
+#[derive(Clone, Debug, Serialize)]
+pub struct EdgeConnection<T> {
+    pub edges: Vec<Edge<T>>,
+    pub page_info: PageInfo,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Edge<T> {
+    pub node: T,
+    pub cursor: Cursor,
+}
+
+#[derive(Clone, Debug, Default, Serialize, JsonSchema)]
 pub struct PageInfo {
     pub next_cursor: Option<Cursor>,
     pub prev_cursor: Option<Cursor>,
+    /// The 1-based page number, only set when paginating by offset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_page: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_pages: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_count: Option<i64>,
+}
+
+impl<T> Connection<T> {
+    /// Converts every node, e.g. into a DTO, keeping the page info
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Connection<U> {
+        Connection {
+            nodes: self.nodes.into_iter().map(f).collect(),
+            page_info: self.page_info,
+        }
+    }
+}
+
+impl<T> EdgeConnection<T> {
+    /// Converts every node, e.g. into a DTO, keeping its cursor and the page info
+    pub fn map<U>(self, mut f: impl FnMut(T) -> U) -> EdgeConnection<U> {
+        EdgeConnection {
+            edges: self
+                .edges
+                .into_iter()
+                .map(|edge| Edge {
+                    node: f(edge.node),
+                    cursor: edge.cursor,
+                })
+                .collect(),
+            page_info: self.page_info,
+        }
+    }
+}
+
+impl<T> From<EdgeConnection<T>> for Connection<T> {
+    fn from(connection: EdgeConnection<T>) -> Self {
+        Self {
+            nodes: connection.edges.into_iter().map(|edge| edge.node).collect(),
+            page_info: connection.page_info,
+        }
+    }
 }
 
 impl<T: JsonSchema> JsonSchema for Connection<T> {
@@ -41,3 +112,35 @@ impl<T: JsonSchema> JsonSchema for Connection<T> {
         ConnectionWithSchema::<T>::json_schema(gen)
     }
 }
+
+impl<T: JsonSchema> JsonSchema for EdgeConnection<T> {
+    fn schema_name() -> String {
+        format!("{}EdgeConnection", T::schema_name())
+    }
+
+    fn schema_id() -> Cow<'static, str> {
+        Cow::Owned(format!(
+            "{}::EdgeConnection<{}>",
+            module_path!(),
+            T::schema_id()
+        ))
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        EdgeConnectionWithSchema::<T>::json_schema(gen)
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Edge<T> {
+    fn schema_name() -> String {
+        format!("{}Edge", T::schema_name())
+    }
+
+    fn schema_id() -> Cow<'static, str> {
+        Cow::Owned(format!("{}::Edge<{}>", module_path!(), T::schema_id()))
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        EdgeWithSchema::<T>::json_schema(gen)
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+
+use sqlx::{postgres::PgRow, PgConnection};
+
+use crate::filter::ast::{Aggregate, Expr, Var};
+use crate::{Error, FieldType, FieldValue, Filter, Model};
+
+use super::select::{generate_having_clause, generate_join_clause, generate_where_clause, Select};
+use super::util::{build_query, decode_column};
+
+/// Computes aggregates over the rows matching the filters, optionally grouped by fields.
+/// Every result row is a map from the group_by fields and aggregate aliases to their values
+#[derive(Clone, Debug)]
+pub struct Aggregation<T: Model> {
+    select: Select<T>,
+    group_by: Vec<String>,
+    selections: Vec<Selection>,
+    having: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+struct Selection {
+    alias: String,
+    aggregate: Aggregate,
+    /// counts the rows of the model when None
+    field: Option<String>,
+}
+
+impl<T: Model> Aggregation<T> {
+    pub(crate) fn new(select: Select<T>) -> Self {
+        Self {
+            select,
+            group_by: vec![],
+            selections: vec![],
+            having: vec![],
+        }
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.select = self.select.with_filter(filter);
+        self
+    }
+
+    /// Groups the results by a field, which may be a relation path like `bakery.name`
+    pub fn group_by(mut self, field: &str) -> Self {
+        self.group_by.push(field.into());
+        self
+    }
+
+    /// Filters the groups, using the filter language extended with aggregates, e.g. `sum(cakes.price) > "10"`
+    pub fn having(mut self, filter: &str) -> Self {
+        self.having.push(filter.into());
+        self
+    }
+
+    /// Counts the rows of the model, rows are counted once even when joined with relations
+    pub fn count(mut self, alias: &str) -> Self {
+        self.selections.push(Selection {
+            alias: alias.into(),
+            aggregate: Aggregate::CountDistinct,
+            field: None,
+        });
+        self
+    }
+
+    pub fn count_distinct(self, alias: &str, field: &str) -> Self {
+        self.aggregate(alias, Aggregate::CountDistinct, field)
+    }
+
+    pub fn sum(self, alias: &str, field: &str) -> Self {
+        self.aggregate(alias, Aggregate::Sum, field)
+    }
+
+    pub fn avg(self, alias: &str, field: &str) -> Self {
+        self.aggregate(alias, Aggregate::Avg, field)
+    }
+
+    pub fn min(self, alias: &str, field: &str) -> Self {
+        self.aggregate(alias, Aggregate::Min, field)
+    }
+
+    pub fn max(self, alias: &str, field: &str) -> Self {
+        self.aggregate(alias, Aggregate::Max, field)
+    }
+
+    /// Applies the aggregate to a field or relation path. Counting a relation, e.g. `cakes`,
+    /// counts the distinct related rows
+    pub fn aggregate(mut self, alias: &str, aggregate: Aggregate, field: &str) -> Self {
+        self.selections.push(Selection {
+            alias: alias.into(),
+            aggregate,
+            field: field.to_string().into(),
+        });
+        self
+    }
+
+    pub async fn fetch_all(
+        self,
+        executor: &mut PgConnection,
+    ) -> Result<Vec<HashMap<String, FieldValue>>, Error> {
+        let (statement, var_bindings, columns) = self.prepare()?;
+
+        let rows = build_query(&statement, var_bindings)
+            .fetch_all(executor)
+            .await?;
+
+        rows.iter().map(|row| decode_row(row, &columns)).collect()
+    }
+
+    pub async fn fetch_one(
+        self,
+        executor: &mut PgConnection,
+    ) -> Result<HashMap<String, FieldValue>, Error> {
+        let (statement, var_bindings, columns) = self.prepare()?;
+
+        let row = build_query(&statement, var_bindings)
+            .fetch_one(executor)
+            .await?;
+
+        decode_row(&row, &columns)
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn prepare(&self) -> Result<(String, Vec<FieldValue>, Vec<(String, FieldType)>), Error> {
+        if self.selections.is_empty() && self.group_by.is_empty() {
+            return Err(Error::bad_request(
+                "invalid aggregation: at least one aggregate or group_by field is required",
+            ));
+        }
+
+        let model_def = T::definition();
+
+        let mut vars = vec![];
+        let mut selects = vec![];
+        let mut group_by = vec![];
+        let mut columns: Vec<(String, FieldType)> = vec![];
+        let mut aggregated_vars = vec![];
+
+        for field in self.group_by.iter() {
+            let var = Var::from_path(field);
+            let def = var.resolve_definition(&model_def)?;
+
+            let (reference, _, _) = Expr::Var(var.clone()).to_sql::<T>(0);
+
+            selects.push(format!("{} AS \"{}\"", reference, field));
+            group_by.push(reference);
+            vars.push(var);
+            columns.push((field.clone(), def.type_));
+        }
+
+        for selection in self.selections.iter() {
+            if !is_identifier(&selection.alias) {
+                return Err(Error::bad_request(&format!(
+                    "invalid aggregation: alias {} may only contain letters, digits and underscores",
+                    selection.alias
+                )));
+            }
+
+            if columns.iter().any(|(name, _)| name == &selection.alias) {
+                return Err(Error::bad_request(&format!(
+                    "invalid aggregation: duplicate column {}",
+                    selection.alias
+                )));
+            }
+
+            let (aggregate, var, type_) = match &selection.field {
+                None => (
+                    selection.aggregate.clone(),
+                    Var::Leaf(T::id_field_name()),
+                    FieldType::Int,
+                ),
+                Some(field) => {
+                    let var = Var::from_path(field);
+
+                    match (&selection.aggregate, var.resolve_definition(&model_def)) {
+                        (Aggregate::Count | Aggregate::CountDistinct, Err(_)) => (
+                            Aggregate::CountDistinct,
+                            var.resolve_relation_id(&model_def)?,
+                            FieldType::Int,
+                        ),
+                        (_, Err(err)) => return Err(err),
+                        (aggregate, Ok(def)) => {
+                            let type_ = aggregate.result_type(&def.type_)?;
+                            (aggregate.clone(), var, type_)
+                        }
+                    }
+                }
+            };
+
+            // min, max and distinct counts are unaffected by repeated rows
+            if matches!(
+                aggregate,
+                Aggregate::Count | Aggregate::Sum | Aggregate::Avg
+            ) {
+                aggregated_vars.push((selection.alias.clone(), var.clone()));
+            }
+
+            let (reference, _, _) = Expr::Agg(aggregate, var.clone()).to_sql::<T>(0);
+
+            selects.push(format!("{} AS \"{}\"", reference, selection.alias));
+            vars.push(var);
+            columns.push((selection.alias.clone(), type_));
+        }
+
+        let mut predicates = vec![];
+        let mut var_bindings = vec![];
+
+        let filters = self.select.build_filters()?;
+
+        // filters on a relation repeat the rows joined with several matches, so they only select
+        // the ids and every matching row is aggregated once
+        if !filters.is_empty() {
+            let table_name = T::table_name();
+            let id_field_name = T::id_field_name();
+            let (from_clause, b) = self.select.prepare_from_clause(filters)?;
+
+            predicates.push(format!(
+                "{}.{} IN (SELECT {}.{} {})",
+                table_name, id_field_name, table_name, id_field_name, from_clause
+            ));
+            var_bindings.extend(b);
+        }
+
+        predicates.extend(self.select.soft_delete_predicate());
+
+        let mut having = vec![];
+
+        for filter in self.having.iter() {
+            let expr = Expr::from_having_str::<T>(filter)?;
+            let (sql, v, b) = expr.to_sql::<T>(var_bindings.len());
+
+            having.push(sql);
+            vars.extend(v);
+            var_bindings.extend(b);
+        }
+
+        // every has_many path in the joins repeats the rows of the other paths, so an aggregate is
+        // only computed when its own path contains all of them
+        for (alias, var) in aggregated_vars.iter() {
+            let path = var.to_many_path(&model_def)?;
+
+            for other in vars.iter() {
+                if !path.starts_with(&other.to_many_path(&model_def)?) {
+                    return Err(Error::bad_request(&format!(
+                        "invalid aggregation: {} would count rows repeated by the join with {}",
+                        alias,
+                        other.to_string()
+                    )));
+                }
+            }
+        }
+
+        let join_clause = generate_join_clause::<T>(&vars)?;
+        let where_clause = generate_where_clause(&predicates);
+        let having_clause = generate_having_clause(&having);
+
+        let (group_by_clause, order_by_clause) = if group_by.is_empty() {
+            ("".to_string(), "".to_string())
+        } else {
+            (
+                format!("GROUP BY {}", group_by.join(", ")),
+                format!("ORDER BY {}", group_by.join(", ")),
+            )
+        };
+
+        let statement = format!(
+            "
+                SELECT {}
+                FROM {}
+                {}
+                {}
+                {}
+                {}
+                {}
+            ",
+            selects.join(", "),
+            T::table_name(),
+            join_clause,
+            where_clause,
+            group_by_clause,
+            having_clause,
+            order_by_clause
+        );
+
+        tracing::info!("{}", statement);
+
+        Ok((statement, var_bindings, columns))
+    }
+}
+
+fn decode_row(
+    row: &PgRow,
+    columns: &[(String, FieldType)],
+) -> Result<HashMap<String, FieldValue>, Error> {
+    columns
+        .iter()
+        .map(|(name, type_)| Ok((name.clone(), decode_column(row, name, type_)?)))
+        .collect()
+}
+
+/// Aliases are quoted in the statement, so they are restricted to plain identifiers
+fn is_identifier(alias: &str) -> bool {
+    let mut chars = alias.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
@@ -0,0 +1,46 @@
+use sqlx::{postgres::PgRow, FromRow, PgConnection};
+
+use crate::Error;
+use crate::Model;
+
+use super::select::Select;
+
+pub struct Batches<T: Model> {
+    select: Select<T>,
+    exhausted: bool,
+}
+
+impl<T: Model> Batches<T> {
+    pub(crate) fn new(select: Select<T>) -> Self {
+        Self {
+            select,
+            exhausted: false,
+        }
+    }
+}
+
+impl<T> Batches<T>
+where
+    T: Clone + Model + for<'a> FromRow<'a, PgRow> + Unpin + Sized + Send,
+{
+    /// Fetches the next batch, resuming after the last row of the previous one.
+    /// Returns None once every row has been visited.
+    pub async fn next(&mut self, executor: &mut PgConnection) -> Result<Option<Vec<T>>, Error> {
+        if self.exhausted {
+            return Ok(None);
+        }
+
+        let connection = self.select.clone().fetch_page(executor).await?;
+
+        match connection.page_info.next_cursor {
+            Some(cursor) => self.select.cursor = cursor.into(),
+            None => self.exhausted = true,
+        }
+
+        if connection.nodes.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(connection.nodes.into())
+    }
+}
@@ -1,4 +1,4 @@
-use sqlx::PgConnection;
+use sqlx::{Connection, PgConnection};
 
 use crate::Error;
 use crate::Model;
@@ -17,24 +17,60 @@ impl<'a, T: Model> BulkCreate<'a, T> {
         }
     }
 
+    /// Copies every row into the table. Like `create`, null values of columns with a default are
+    /// replaced by the default. Since COPY can't fall back to the default per row, the rows of a
+    /// model with defaults are copied into a temporary staging table first and inserted from there
     pub async fn execute(self, executor: &mut PgConnection) -> Result<(), Error> {
         let table_name = T::table_name();
-        let fields = T::field_definitions()
+        // qualified with pg_temp so that a table of the same name can't be picked up instead
+        let staging_table = format!("pg_temp._staging_{}", table_name);
+
+        // read only columns and timestamps are left to the database
+        let defs = T::field_definitions()
             .into_iter()
+            .filter(|def| !(def.read_only || def.created_at || def.updated_at))
+            .collect::<Vec<_>>();
+
+        let fields = defs
+            .iter()
             .map(|field| format!("\"{}\"", field.name))
             .collect::<Vec<String>>()
             .join(", ");
 
+        let staged = defs.iter().any(|def| def.default.is_some());
+
+        // runs in a transaction, or a savepoint within one, so a failure doesn't leave the staging table behind
+        let mut tx = executor.begin().await?;
+
+        let target = if staged {
+            sqlx::query(&format!("DROP TABLE IF EXISTS {}", staging_table))
+                .execute(&mut *tx)
+                .await?;
+
+            let statement = format!(
+                "CREATE TEMPORARY TABLE {} ON COMMIT DROP AS SELECT {} FROM {} WITH NO DATA",
+                staging_table, fields, table_name
+            );
+
+            tracing::info!("{}", statement);
+
+            sqlx::query(&statement).execute(&mut *tx).await?;
+
+            &staging_table
+        } else {
+            &table_name
+        };
+
         let statement = format!(
             "COPY {} ({}) FROM stdin WITH (FORMAT csv, HEADER false, NULL 'null')",
-            table_name, fields
+            target, fields
         );
 
-        let mut writer = executor.copy_in_raw(&statement).await?;
+        let mut writer = tx.copy_in_raw(&statement).await?;
 
         for record in self.iterator {
-            let values = T::field_definitions()
-                .into_iter()
+            let values = defs
+                .iter()
                 .map(|def| record.field_value(&def.name).unwrap().to_csv_string())
                 .collect::<Vec<String>>()
                 .join(",");
@@ -45,6 +81,33 @@ impl<'a, T: Model> BulkCreate<'a, T> {
 
         writer.finish().await?;
 
+        if staged {
+            let values = defs
+                .iter()
+                .map(|def| match &def.default {
+                    Some(default) => format!("COALESCE(_staged.\"{}\", {})", def.name, default),
+                    None => format!("_staged.\"{}\"", def.name),
+                })
+                .collect::<Vec<String>>()
+                .join(", ");
+
+            let statement = format!(
+                "INSERT INTO {} ({}) SELECT {} FROM {} AS _staged",
+                table_name, fields, values, staging_table
+            );
+
+            tracing::info!("{}", statement);
+
+            sqlx::query(&statement).execute(&mut *tx).await?;
+
+            // a savepoint doesn't end the surrounding transaction, so the table isn't dropped on commit yet
+            sqlx::query(&format!("DROP TABLE {}", staging_table))
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
         Ok(())
     }
 }
@@ -1,27 +1,27 @@
 use std::marker::PhantomData;
 
 use sqlx::PgConnection;
-use uuid::Uuid;
 
 use crate::relation::Reference;
 use crate::Error;
-use crate::Model;
+use crate::{FieldValue, Model};
 
 pub struct BulkCreateAssociation<'a, T: Model> {
     relation_name: &'a str,
-    iterator: Box<dyn Iterator<Item = (Uuid, Uuid)> + 'a>,
+    iterator: Box<dyn Iterator<Item = (FieldValue, FieldValue)> + 'a>,
     _marker: PhantomData<T>,
 }
 
 impl<'a, T: Model> BulkCreateAssociation<'a, T> {
-    pub(crate) fn new<I>(relation_name: &'a str, iter: I) -> Self
+    pub(crate) fn new<I, A>(relation_name: &'a str, iter: I) -> Self
     where
-        I: Iterator<Item = (Uuid, Uuid)> + 'a,
+        I: Iterator<Item = (T::Id, A)> + 'a,
+        A: Into<FieldValue>,
     {
         Self {
             relation_name,
-            iterator: Box::new(iter),
-            _marker: PhantomData::default(),
+            iterator: Box::new(iter.map(|(from_id, to_id)| (from_id.into(), to_id.into()))),
+            _marker: PhantomData,
         }
     }
 
@@ -47,7 +47,7 @@ impl<'a, T: Model> BulkCreateAssociation<'a, T> {
                 let mut writer = executor.copy_in_raw(&statement).await?;
 
                 for (from_id, to_id) in self.iterator {
-                    let row = format!("{},{}\n", from_id.to_string(), to_id.to_string());
+                    let row = format!("{},{}\n", from_id.to_csv_string(), to_id.to_csv_string());
                     writer.send(row.as_bytes()).await?;
                 }
 
@@ -0,0 +1,216 @@
+use sqlx::{Connection, PgConnection};
+
+use crate::Error;
+use crate::Model;
+
+use super::upsert::ConflictTarget;
+use super::util::version_increment;
+
+/// How many rows of an upsert were inserted and how many updated existing rows
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UpsertCounts {
+    pub inserted: i64,
+    pub updated: i64,
+}
+
+pub struct BulkUpsert<'a, T: Model> {
+    iterator: Box<dyn Iterator<Item = T> + 'a>,
+}
+
+impl<'a, T: Model> BulkUpsert<'a, T> {
+    pub(crate) fn new<I>(iter: I) -> Self
+    where
+        I: Iterator<Item = T> + 'a,
+    {
+        Self {
+            iterator: Box::new(iter),
+        }
+    }
+
+    /// Copies every row into a temporary staging table, then inserts them or updates the existing
+    /// rows like `upsert` does. If a primary key occurs more than once the last row wins.
+    /// Null values of columns with a default are replaced by the default when inserting and keep
+    /// the stored value when updating.
+    /// Rows with a version that doesn't match the stored one, or conflicting with a soft deleted
+    /// row, are neither inserted nor updated
+    pub async fn execute(self, executor: &mut PgConnection) -> Result<UpsertCounts, Error> {
+        let table_name = T::table_name();
+        // qualified with pg_temp so that a table of the same name can't be picked up instead
+        let staging_table = format!("pg_temp._staging_{}", table_name);
+
+        // read only columns and timestamps are left to the database
+        let defs = T::field_definitions()
+            .into_iter()
+            .filter(|def| !(def.read_only || ((def.created_at || def.updated_at) && !def.version)))
+            .collect::<Vec<_>>();
+
+        let columns = defs
+            .iter()
+            .map(|def| def.name.as_str())
+            .collect::<Vec<&str>>()
+            .join(", ");
+
+        // runs in a transaction, or a savepoint within one, so a failure doesn't leave the staging table behind
+        let mut tx = executor.begin().await?;
+
+        // the staging table only takes the column types, so constraints and defaults don't apply yet
+        sqlx::query(&format!("DROP TABLE IF EXISTS {}", staging_table))
+            .execute(&mut *tx)
+            .await?;
+
+        let statement = format!(
+            "CREATE TEMPORARY TABLE {} ON COMMIT DROP AS SELECT {} FROM {} WITH NO DATA",
+            staging_table, columns, table_name
+        );
+
+        tracing::info!("{}", statement);
+
+        sqlx::query(&statement).execute(&mut *tx).await?;
+
+        let statement = format!(
+            "COPY {} ({}) FROM stdin WITH (FORMAT csv, HEADER false, NULL 'null')",
+            staging_table, columns
+        );
+
+        let mut writer = tx.copy_in_raw(&statement).await?;
+
+        for record in self.iterator {
+            let values = defs
+                .iter()
+                .map(|def| record.field_value(&def.name).unwrap().to_csv_string())
+                .collect::<Vec<String>>()
+                .join(",");
+
+            let row = format!("{}\n", values);
+            writer.send(row.as_bytes()).await?;
+        }
+
+        writer.finish().await?;
+
+        let statement = Self::upsert_statement(&staging_table)?;
+
+        tracing::info!("{}", statement);
+
+        let (inserted, updated): (i64, i64) =
+            sqlx::query_as(&statement).fetch_one(&mut *tx).await?;
+
+        // a savepoint doesn't end the surrounding transaction, so the table isn't dropped on commit yet
+        sqlx::query(&format!("DROP TABLE {}", staging_table))
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(UpsertCounts { inserted, updated })
+    }
+
+    fn upsert_statement(staging_table: &str) -> Result<String, Error> {
+        let table_name = T::table_name();
+        let conflict_target = ConflictTarget::PrimaryKey.to_sql::<T>()?;
+
+        let primary_key_columns = T::field_definitions()
+            .into_iter()
+            .filter(|def| def.primary_key)
+            .map(|def| def.name)
+            .collect::<Vec<_>>();
+
+        let primary_key = primary_key_columns
+            .iter()
+            .map(|column| format!("_staged.{}", column))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let existing_condition = primary_key_columns
+            .iter()
+            .map(|column| format!("_existing.{} = _staged.{}", column, column))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+
+        let existing = format!("_existing.{}", primary_key_columns[0]);
+
+        let mut columns = vec![];
+        let mut values = vec![];
+        let mut set_clauses = vec![];
+        let mut conditions = vec![];
+
+        for def in T::field_definitions().into_iter() {
+            if def.read_only {
+                continue;
+            }
+
+            let reference = format!("{}.{}", table_name, def.name);
+            let excluded = format!("EXCLUDED.{}", def.name);
+
+            let value = if (def.created_at || def.updated_at) && !def.version {
+                "now()".to_string()
+            } else if let Some(default) = &def.default {
+                // like Upsert, columns left to their default keep their current value on conflict
+                format!(
+                    "CASE WHEN _staged.{col} IS NOT NULL THEN _staged.{col} \
+                    WHEN {existing} IS NOT NULL THEN _existing.{col} ELSE {default} END",
+                    col = def.name,
+                    existing = existing,
+                    default = default
+                )
+            } else {
+                format!("_staged.{}", def.name)
+            };
+
+            // deleted rows are left deleted rather than restored
+            let set_value = if def.version {
+                conditions.push(format!("{} = {}", reference, excluded));
+                Some(version_increment(&def, &reference))
+            } else if def.soft_delete {
+                conditions.push(format!("{} IS NULL", reference));
+                None
+            } else if def.immutable {
+                Some(reference)
+            } else {
+                Some(excluded)
+            };
+
+            if let Some(set_value) = set_value {
+                set_clauses.push(format!("{} = {}", def.name, set_value));
+            }
+
+            columns.push(def.name);
+            values.push(value);
+        }
+
+        let where_clause = if conditions.is_empty() {
+            "".to_string()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        };
+
+        // xmax is only set on rows that existed before, which tells the updated rows from the inserted ones
+        let statement = format!(
+            "
+                WITH upserted AS (
+                    INSERT INTO {table} ({columns})
+                    SELECT {values} FROM (
+                        SELECT DISTINCT ON ({primary_key}) _staged.*
+                        FROM {staging} AS _staged
+                        ORDER BY {primary_key}, _staged.ctid DESC
+                    ) AS _staged
+                    LEFT JOIN {table} AS _existing ON {existing_condition}
+                    ON CONFLICT {conflict_target} DO UPDATE SET {set_clauses}{where_clause}
+                    RETURNING xmax = 0 AS inserted
+                )
+                SELECT COUNT(*) FILTER (WHERE inserted), COUNT(*) FILTER (WHERE NOT inserted)
+                FROM upserted
+            ",
+            table = table_name,
+            columns = columns.join(", "),
+            values = values.join(", "),
+            primary_key = primary_key,
+            staging = staging_table,
+            existing_condition = existing_condition,
+            set_clauses = set_clauses.join(", "),
+            conflict_target = conflict_target,
+            where_clause = where_clause,
+        );
+
+        Ok(statement)
+    }
+}
@@ -1,7 +1,10 @@
 use sqlx::{postgres::PgRow, FromRow, PgConnection};
 
 use crate::Error;
-use crate::{crud::util::build_query, FieldValue, Model};
+use crate::{
+    crud::util::{build_query, build_query_as, inline_bindings, insert_values},
+    FieldValue, Model,
+};
 
 #[derive(Debug)]
 pub struct Create<'a, T> {
@@ -17,33 +20,95 @@ where
     }
 
     pub async fn execute(self, executor: &mut PgConnection) -> Result<(), Error> {
+        let (statement, var_bindings) = self.to_sql()?;
+
+        build_query(&statement, var_bindings)
+            .execute(executor)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Inserts the row and returns it as stored, including any columns filled in by the database
+    pub async fn execute_returning(self, executor: &mut PgConnection) -> Result<T, Error> {
+        let (statement, var_bindings) = self.to_sql()?;
+        let statement = format!("{} RETURNING *", statement);
+
+        let created = build_query_as(&statement, var_bindings)
+            .fetch_one(executor)
+            .await?;
+
+        Ok(created)
+    }
+
+    pub fn to_sql(&self) -> Result<(String, Vec<FieldValue>), Error> {
         let table_name = T::table_name();
-        let fields = self.value.fields()?;
+        let (values, var_bindings) = insert_values(self.value.fields()?);
 
-        let columns = fields
+        let columns = values
             .iter()
             .map(|(def, _)| def.name.as_ref())
             .collect::<Vec<&str>>()
             .join(", ");
 
-        let placeholder_values = fields
+        let placeholder_values = values
             .iter()
-            .enumerate()
-            .map(|(i, _)| format!("${}", i + 1))
-            .collect::<Vec<String>>()
+            .map(|(_, value)| value.as_ref())
+            .collect::<Vec<&str>>()
             .join(", ");
 
-        let var_bindings: Vec<FieldValue> = fields.into_iter().map(|(_, value)| value).collect();
-
         let statement = format!(
             "INSERT INTO {} ({}) VALUES ({})",
             table_name, columns, placeholder_values
         );
 
-        build_query(&statement, var_bindings)
-            .execute(executor)
+        Ok((statement, var_bindings))
+    }
+
+    /// Renders the statement with its bindings inlined, e.g. to paste it into psql
+    pub fn to_pretty_sql(&self) -> Result<String, Error> {
+        let (statement, var_bindings) = self.to_sql()?;
+        Ok(inline_bindings(&statement, &var_bindings))
+    }
+}
+
+#[derive(Debug)]
+pub struct CreateIfAbsent<'a, T> {
+    create: Create<'a, T>,
+}
+
+impl<'a, T> CreateIfAbsent<'a, T>
+where
+    T: Model + for<'b> FromRow<'b, PgRow> + Unpin + Sized + Send,
+{
+    pub(crate) fn new(value: &'a T) -> Self {
+        Self {
+            create: Create::new(value),
+        }
+    }
+
+    /// Inserts the row unless it conflicts with an existing one. Returns the stored row,
+    /// or None if nothing was inserted
+    pub async fn execute(self, executor: &mut PgConnection) -> Result<Option<T>, Error> {
+        let (statement, var_bindings) = self.to_sql()?;
+
+        let created = build_query_as(&statement, var_bindings)
+            .fetch_optional(executor)
             .await?;
 
-        Ok(())
+        Ok(created)
+    }
+
+    pub fn to_sql(&self) -> Result<(String, Vec<FieldValue>), Error> {
+        let (statement, var_bindings) = self.create.to_sql()?;
+        let statement = format!("{} ON CONFLICT DO NOTHING RETURNING *", statement);
+
+        Ok((statement, var_bindings))
+    }
+
+    /// Renders the statement with its bindings inlined, e.g. to paste it into psql
+    pub fn to_pretty_sql(&self) -> Result<String, Error> {
+        let (statement, var_bindings) = self.to_sql()?;
+        Ok(inline_bindings(&statement, &var_bindings))
     }
 }
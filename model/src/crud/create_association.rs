@@ -1,5 +1,4 @@
 use sqlx::{postgres::PgRow, FromRow, PgConnection};
-use uuid::Uuid;
 
 use crate::relation::Reference;
 use crate::Error;
@@ -9,14 +8,14 @@ use crate::{crud::util::build_query, FieldValue, Model};
 pub struct CreateAssociation<'a, T: Model> {
     value: &'a T,
     relation_name: &'a str,
-    associated_id: &'a Uuid,
+    associated_id: FieldValue,
 }
 
 impl<'a, T> CreateAssociation<'a, T>
 where
     T: Model + for<'b> FromRow<'b, PgRow> + Unpin + Sized + Send,
 {
-    pub(crate) fn new(value: &'a T, relation_name: &'a str, associated_id: &'a Uuid) -> Self {
+    pub(crate) fn new(value: &'a T, relation_name: &'a str, associated_id: FieldValue) -> Self {
         Self {
             value,
             relation_name,
@@ -39,13 +38,13 @@ where
         match relation.reference {
             Reference::Via((junction_table, from_ref, to_ref)) => {
                 let statement = format!(
-                    "INSERT INTO {} ({}, {}) VALUES ($1, $2) ON CONFLICT ON CONSTRAINT {}_pkey DO NOTHING",
-                    junction_table, from_ref, to_ref, junction_table
+                    "INSERT INTO {} ({}, {}) VALUES ($1, $2) ON CONFLICT ({}, {}) DO NOTHING",
+                    junction_table, from_ref, to_ref, from_ref, to_ref
                 );
 
                 let var_bindings: Vec<FieldValue> = vec![
                     self.value.id_field_value().into(),
-                    self.associated_id.clone().into(),
+                    self.associated_id.clone(),
                 ];
 
                 build_query(&statement, var_bindings)
@@ -1,14 +1,17 @@
 use std::marker::PhantomData;
 
 use sqlx::{postgres::PgRow, FromRow, PgConnection};
-use uuid::Uuid;
 
 use crate::Error;
-use crate::{crud::util::build_query, Model};
+use crate::{
+    crud::util::{build_query, inline_bindings, touch_clauses},
+    FieldValue, Model,
+};
 
 #[derive(Clone, Debug)]
 pub struct Delete<T: Model> {
-    id: Uuid,
+    id: T::Id,
+    hard: bool,
     _marker: PhantomData<T>,
 }
 
@@ -16,31 +19,56 @@ impl<T> Delete<T>
 where
     T: Model + for<'b> FromRow<'b, PgRow> + Unpin + Sized + Send,
 {
-    pub(crate) fn new(id: Uuid) -> Self {
+    pub(crate) fn new(id: T::Id) -> Self {
         Self {
             id,
-            _marker: PhantomData::default(),
+            hard: false,
+            _marker: PhantomData,
         }
     }
 
+    /// Removes the row even if the model is soft deleted
+    pub(crate) fn hard(mut self) -> Self {
+        self.hard = true;
+        self
+    }
+
     pub async fn execute(self, executor: &mut PgConnection) -> Result<(), Error> {
-        let table_name = T::table_name();
-        let id_field_name = T::id_field_name();
+        let (statement, var_bindings) = self.to_sql();
 
-        let statement = format!("DELETE FROM {} WHERE {} = $1", table_name, id_field_name);
+        build_query(&statement, var_bindings)
+            .execute(executor)
+            .await?;
+
+        Ok(())
+    }
 
-        let var_bindings = vec![self.id.into()];
+    pub fn to_sql(&self) -> (String, Vec<FieldValue>) {
+        let table_name = T::table_name();
+        let id_field_name = T::id_field_name();
 
-        let result = build_query(&statement, var_bindings)
-            .execute(executor)
-            .await;
+        let statement = match T::definition().soft_delete_column() {
+            Some(column) if !self.hard => {
+                let mut set_clauses = vec![format!("{} = now()", column)];
+                set_clauses.extend(touch_clauses(&T::field_definitions()));
 
-        if matches!(result, Err(sqlx::Error::RowNotFound)) {
-            return Ok(());
-        }
+                format!(
+                    "UPDATE {} SET {} WHERE {} = $1 AND {} IS NULL",
+                    table_name,
+                    set_clauses.join(", "),
+                    id_field_name,
+                    column
+                )
+            }
+            _ => format!("DELETE FROM {} WHERE {} = $1", table_name, id_field_name),
+        };
 
-        result?;
+        (statement, vec![self.id.clone().into()])
+    }
 
-        Ok(())
+    /// Renders the statement with its bindings inlined, e.g. to paste it into psql
+    pub fn to_pretty_sql(&self) -> String {
+        let (statement, var_bindings) = self.to_sql();
+        inline_bindings(&statement, &var_bindings)
     }
 }
@@ -1,5 +1,4 @@
 use sqlx::{postgres::PgRow, FromRow, PgConnection};
-use uuid::Uuid;
 
 use crate::relation::Reference;
 use crate::Error;
@@ -9,14 +8,14 @@ use crate::{crud::util::build_query, FieldValue, Model};
 pub struct DeleteAssociation<'a, T: Model> {
     value: &'a T,
     relation_name: &'a str,
-    associated_id: &'a Uuid,
+    associated_id: FieldValue,
 }
 
 impl<'a, T> DeleteAssociation<'a, T>
 where
     T: Model + for<'b> FromRow<'b, PgRow> + Unpin + Sized + Send,
 {
-    pub(crate) fn new(value: &'a T, relation_name: &'a str, associated_id: &'a Uuid) -> Self {
+    pub(crate) fn new(value: &'a T, relation_name: &'a str, associated_id: FieldValue) -> Self {
         Self {
             value,
             relation_name,
@@ -45,7 +44,7 @@ where
 
                 let var_bindings: Vec<FieldValue> = vec![
                     self.value.id_field_value().into(),
-                    self.associated_id.clone().into(),
+                    self.associated_id.clone(),
                 ];
 
                 let result = build_query(&statement, var_bindings)
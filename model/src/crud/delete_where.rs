@@ -0,0 +1,126 @@
+use sqlx::{postgres::PgRow, FromRow, PgConnection};
+
+use crate::Error;
+use crate::{
+    crud::{
+        util::{build_query, build_query_as, inline_bindings, touch_clauses},
+        Select,
+    },
+    FieldValue, Filter, Model,
+};
+
+/// Deletes every row matched by a filter in a single statement, see `Crud::delete_where`
+#[derive(Clone, Debug)]
+pub struct DeleteWhere<T: Model> {
+    select: Select<T>,
+    filtered: bool,
+    all: bool,
+    hard: bool,
+}
+
+impl<T> DeleteWhere<T>
+where
+    T: Model + for<'b> FromRow<'b, PgRow> + Unpin + Sized + Send,
+{
+    pub(crate) fn new(filter: Filter) -> Self {
+        let filtered = !filter.is_empty();
+
+        let mut select = Select::new();
+
+        if filtered {
+            select = select.with_filter(filter);
+        }
+
+        Self {
+            select,
+            filtered,
+            all: false,
+            hard: false,
+        }
+    }
+
+    /// Allows an empty filter to delete every row of the table
+    pub fn all(mut self) -> Self {
+        self.all = true;
+        self
+    }
+
+    /// Removes the rows even if the model has a `soft_delete` field, including rows that
+    /// were soft deleted before
+    pub fn hard(mut self) -> Self {
+        self.hard = true;
+        self.select = self.select.with_deleted();
+        self
+    }
+
+    /// Deletes the matched rows and returns how many were deleted
+    pub async fn execute(self, executor: &mut PgConnection) -> Result<u64, Error> {
+        let (statement, var_bindings) = self.to_sql()?;
+
+        tracing::info!("{}", statement);
+
+        let result = build_query(&statement, var_bindings)
+            .execute(executor)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Like `execute`, but returns the deleted rows
+    pub async fn execute_returning(self, executor: &mut PgConnection) -> Result<Vec<T>, Error> {
+        let (statement, var_bindings) = self.to_sql()?;
+
+        let statement = format!("{} RETURNING *", statement);
+
+        tracing::info!("{}", statement);
+
+        let rows = build_query_as(&statement, var_bindings)
+            .fetch_all(executor)
+            .await?;
+
+        Ok(rows)
+    }
+
+    pub fn to_sql(&self) -> Result<(String, Vec<FieldValue>), Error> {
+        let table_name = T::table_name();
+        let id_field_name = T::id_field_name();
+
+        if !self.filtered && !self.all {
+            return Err(Error::bad_request(
+                "invalid delete: the filter is empty, call all() to delete every row",
+            ));
+        }
+
+        // the matching rows are selected like a select would, so relation filters can be used
+        let filters = self.select.build_filters()?;
+        let (from_clause, var_bindings) = self.select.prepare_from_clause(filters)?;
+
+        let where_clause = format!(
+            "{}.{} IN (SELECT {}.{} {})",
+            table_name, id_field_name, table_name, id_field_name, from_clause
+        );
+
+        let statement = match T::definition().soft_delete_column() {
+            Some(column) if !self.hard => {
+                let mut set_clauses = vec![format!("{} = now()", column)];
+                set_clauses.extend(touch_clauses(&T::field_definitions()));
+
+                format!(
+                    "UPDATE {} SET {} WHERE {}",
+                    table_name,
+                    set_clauses.join(", "),
+                    where_clause
+                )
+            }
+            _ => format!("DELETE FROM {} WHERE {}", table_name, where_clause),
+        };
+
+        Ok((statement, var_bindings))
+    }
+
+    /// Renders the statement with its bindings inlined, e.g. to paste it into psql
+    pub fn to_pretty_sql(&self) -> Result<String, Error> {
+        let (statement, var_bindings) = self.to_sql()?;
+        Ok(inline_bindings(&statement, &var_bindings))
+    }
+}
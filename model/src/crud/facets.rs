@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use sqlx::{PgConnection, Row};
+
+use crate::{Error, FieldType, FieldValue, Model};
+
+use super::select::Select;
+use super::util::{build_query, decode_column};
+
+/// The number of rows matching the filters for one distinct value of a facet field
+#[derive(Clone, Debug, PartialEq)]
+pub struct Facet {
+    pub value: FieldValue,
+    pub count: i64,
+}
+
+/// Counts the rows matching the filters per distinct value of enum and bool fields
+pub struct Facets<T: Model> {
+    select: Select<T>,
+    fields: Vec<String>,
+    exclude_own_filters: bool,
+}
+
+impl<T: Model> Facets<T> {
+    pub(crate) fn new(select: Select<T>, fields: Vec<String>) -> Self {
+        Self {
+            select,
+            fields,
+            exclude_own_filters: false,
+        }
+    }
+
+    /// Ignores the filters on a facet field when counting that field's values, so selecting
+    /// one value doesn't hide the counts of the others
+    pub fn exclude_own_filters(mut self) -> Self {
+        self.exclude_own_filters = true;
+        self
+    }
+
+    /// Fetches the facets of every field. Buckets are ordered by the enum's variants, or true before false,
+    /// with variants matching no rows included at a count of zero. Null values are counted last if present
+    pub async fn fetch(
+        self,
+        executor: &mut PgConnection,
+    ) -> Result<HashMap<String, Vec<Facet>>, Error> {
+        let filters = self.select.build_filters()?;
+
+        let mut results = HashMap::new();
+
+        for field in self.fields.iter() {
+            let def = T::field_definitions()
+                .into_iter()
+                .find(|def| &def.name == field)
+                .ok_or_else(|| Error::bad_request(&format!("invalid facet: {}", field)))?;
+
+            let mut buckets = match &def.type_ {
+                FieldType::Enum(variants) => variants
+                    .iter()
+                    .map(|variant| FieldValue::Enum(variant.clone().into()))
+                    .collect::<Vec<_>>(),
+                FieldType::Bool => {
+                    vec![FieldValue::Bool(Some(true)), FieldValue::Bool(Some(false))]
+                }
+                _ => {
+                    return Err(Error::bad_request(&format!(
+                        "invalid facet: {} is not an enum or bool field",
+                        field
+                    )))
+                }
+            };
+
+            let exprs = filters
+                .iter()
+                .flat_map(|expr| expr.clone().into_conjuncts())
+                .filter(|expr| !(self.exclude_own_filters && expr.only_references(field)))
+                .collect::<Vec<_>>();
+
+            let (from_clause, var_bindings) = self.select.prepare_from_clause(exprs)?;
+
+            let reference = format!("{}.{}", T::table_name(), field);
+
+            let statement = format!(
+                "SELECT {} AS value, COUNT(DISTINCT {}.{}) AS count {} GROUP BY {}",
+                reference,
+                T::table_name(),
+                T::id_field_name(),
+                from_clause,
+                reference
+            );
+
+            tracing::info!("{}", statement);
+
+            let rows = build_query(&statement, var_bindings)
+                .fetch_all(&mut *executor)
+                .await?;
+
+            let counts = rows
+                .iter()
+                .map(|row| {
+                    let value = decode_column(row, "value", &def.type_)?;
+                    let count: i64 = row.try_get("count")?;
+
+                    Ok((value, count))
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            let null_value = def.type_.null_value();
+
+            if counts.iter().any(|(value, _)| value == &null_value) {
+                buckets.push(null_value);
+            }
+
+            let facets = buckets
+                .into_iter()
+                .map(|value| {
+                    let count = counts
+                        .iter()
+                        .find(|(v, _)| v == &value)
+                        .map(|(_, count)| *count)
+                        .unwrap_or_default();
+
+                    Facet { value, count }
+                })
+                .collect();
+
+            results.insert(field.clone(), facets);
+        }
+
+        Ok(results)
+    }
+}
@@ -0,0 +1,364 @@
+use std::collections::{BTreeMap, HashMap};
+
+use futures::future::BoxFuture;
+use serde_json::Value;
+use sqlx::PgConnection;
+
+use crate::relation::Reference;
+use crate::{Error, FieldType, Model, ModelDef, ModelId, RelationDef, SortDirection};
+
+/// The relation paths passed to `Select::include`, merged into a tree so that
+/// every relation is loaded once per level
+#[derive(Clone, Debug, Default)]
+pub(crate) struct IncludeTree(BTreeMap<String, IncludeTree>);
+
+impl IncludeTree {
+    pub(crate) fn insert(&mut self, path: &str) {
+        let mut tree = self;
+
+        for name in path.split('.') {
+            tree = tree.0.entry(name.to_string()).or_default();
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// A relation passed to `Select::include_limited`, loading the first `limit` related rows per node
+#[derive(Clone, Debug)]
+pub(crate) struct LimitedInclude {
+    pub(crate) name: String,
+    pub(crate) sort_by: String,
+    pub(crate) direction: SortDirection,
+    pub(crate) limit: i64,
+}
+
+/// Loads the relations passed to both `include` and `include_limited`. Returns the relations of each node in the order of `nodes`
+pub(crate) async fn load_relations<T: Model>(
+    executor: &mut PgConnection,
+    nodes: &[&T],
+    tree: &IncludeTree,
+    limited: &[LimitedInclude],
+) -> Result<Vec<HashMap<String, Value>>, Error> {
+    let mut results = load_includes(executor, nodes, tree).await?;
+
+    if !limited.is_empty() {
+        let limited_results = load_limited_includes(executor, nodes, limited).await?;
+
+        for (result, limited_result) in results.iter_mut().zip(limited_results) {
+            result.extend(limited_result);
+        }
+    }
+
+    Ok(results)
+}
+
+/// Loads the included relations of every node with one query per relation and level.
+/// Returns the relations of each node in the order of `nodes`
+async fn load_includes<T: Model>(
+    executor: &mut PgConnection,
+    nodes: &[&T],
+    tree: &IncludeTree,
+) -> Result<Vec<HashMap<String, Value>>, Error> {
+    let mut results = vec![HashMap::new(); nodes.len()];
+
+    let model_def = T::definition();
+    let relation_defs = T::relation_definitions();
+
+    for (name, subtree) in tree.0.iter() {
+        let relation = find_relation(&relation_defs, name)?;
+        let (key_column, key_type) = key_column(&model_def, relation)?;
+
+        let keys = node_keys(nodes, &key_column)?;
+
+        let values = load_level(executor, relation, keys, &key_type, subtree).await?;
+
+        for (result, value) in results.iter_mut().zip(values) {
+            result.insert(name.clone(), value);
+        }
+    }
+
+    Ok(results)
+}
+
+/// Loads the relations passed to `include_limited` with one query per relation, each selecting
+/// the first rows per node through a lateral join. Returns the relations of each node in the order of `nodes`
+async fn load_limited_includes<T: Model>(
+    executor: &mut PgConnection,
+    nodes: &[&T],
+    includes: &[LimitedInclude],
+) -> Result<Vec<HashMap<String, Value>>, Error> {
+    let mut results = vec![HashMap::new(); nodes.len()];
+
+    let table_name = T::table_name();
+    let id_field_name = T::id_field_name();
+    let relation_defs = T::relation_definitions();
+
+    let keys = node_keys(nodes, &id_field_name)?;
+
+    let mut distinct_keys = keys.iter().flatten().cloned().collect::<Vec<_>>();
+    distinct_keys.sort();
+    distinct_keys.dedup();
+
+    let keys_param = format!("$1::text[]::{}[]", T::Id::field_type().sql_type());
+
+    for include in includes.iter() {
+        let relation = find_relation(&relation_defs, &include.name)?;
+
+        let related_def = &relation.model_definition;
+        let related_table = (related_def.table_name)();
+        let related_id = (related_def.id_field_name)();
+
+        if !(related_def.field_definitions)()
+            .iter()
+            .any(|def| def.name == include.sort_by)
+        {
+            return Err(Error::bad_request(&format!(
+                "invalid include: undefined field {} of relation {}",
+                include.sort_by, include.name
+            )));
+        }
+
+        let direction = match include.direction {
+            SortDirection::Ascending => "ASC",
+            SortDirection::Descending => "DESC",
+        };
+
+        let soft_delete_condition = match related_def.soft_delete_column() {
+            Some(column) => format!("AND r.{} IS NULL", column),
+            None => "".into(),
+        };
+
+        // ties are broken by id so that the selected rows are stable
+        let order_by = format!(
+            "ORDER BY r.{} {}, r.{} {}",
+            include.sort_by, direction, related_id, direction
+        );
+
+        // the rank keeps the rows in order once they leave the lateral join
+        let rank = format!("row_number() OVER ({}) AS _rank", order_by);
+
+        let lateral = match &relation.reference {
+            Reference::To(column) => format!(
+                "SELECT r.*, {} FROM {} AS r WHERE r.{} = p.{} {} {} LIMIT {}",
+                rank,
+                related_table,
+                column,
+                id_field_name,
+                soft_delete_condition,
+                order_by,
+                include.limit
+            ),
+            Reference::Via((junction_table, from_reference, to_reference)) => format!(
+                "SELECT r.*, {} FROM {} AS j JOIN {} AS r ON r.{} = j.{} WHERE j.{} = p.{} {} {} LIMIT {}",
+                rank,
+                junction_table,
+                related_table,
+                related_id,
+                to_reference,
+                from_reference,
+                id_field_name,
+                soft_delete_condition,
+                order_by,
+                include.limit
+            ),
+            Reference::From(_) => {
+                return Err(Error::bad_request(&format!(
+                    "invalid include: {} refers to a single row and can't be limited",
+                    include.name
+                )))
+            }
+        };
+
+        let statement = format!(
+            "SELECT p.{}::text AS _key, to_jsonb(l.*) - '_rank' AS _row FROM {} AS p LEFT JOIN LATERAL ({}) AS l ON TRUE WHERE p.{} = ANY({}) ORDER BY p.{}, l._rank",
+            id_field_name, table_name, lateral, id_field_name, keys_param, id_field_name
+        );
+
+        tracing::info!("{}", statement);
+
+        let rows: Vec<(String, Option<Value>)> = sqlx::query_as(&statement)
+            .bind(distinct_keys.clone())
+            .fetch_all(&mut *executor)
+            .await?;
+
+        let mut grouped: HashMap<String, Vec<Value>> = HashMap::new();
+
+        for (key, row) in rows.into_iter() {
+            let rows = grouped.entry(key).or_default();
+            rows.extend(row);
+        }
+
+        for (result, key) in results.iter_mut().zip(keys.iter()) {
+            let rows = key
+                .as_ref()
+                .and_then(|key| grouped.get(key).cloned())
+                .unwrap_or_default();
+
+            result.insert(include.name.clone(), Value::Array(rows));
+        }
+    }
+
+    Ok(results)
+}
+
+/// Loads the included relations of json encoded rows of the model, nesting them under the relation name
+fn load_children<'a>(
+    executor: &'a mut PgConnection,
+    model_def: &'a ModelDef,
+    parents: &'a mut [Value],
+    tree: &'a IncludeTree,
+) -> BoxFuture<'a, Result<(), Error>> {
+    Box::pin(async move {
+        let relation_defs = (model_def.relation_definitions)();
+
+        for (name, subtree) in tree.0.iter() {
+            let relation = find_relation(&relation_defs, name)?;
+            let (key_column, key_type) = key_column(model_def, relation)?;
+
+            let keys = parents
+                .iter()
+                .map(|parent| json_key(&parent[&key_column]))
+                .collect();
+
+            let values = load_level(executor, relation, keys, &key_type, subtree).await?;
+
+            for (parent, value) in parents.iter_mut().zip(values) {
+                if let Value::Object(parent) = parent {
+                    parent.insert(name.clone(), value);
+                }
+            }
+        }
+
+        Ok(())
+    })
+}
+
+/// Fetches the related rows for every parent key, followed by their own includes
+async fn load_level(
+    executor: &mut PgConnection,
+    relation: &RelationDef,
+    keys: Vec<Option<String>>,
+    key_type: &FieldType,
+    subtree: &IncludeTree,
+) -> Result<Vec<Value>, Error> {
+    let related_table = (relation.model_definition.table_name)();
+    let related_id = (relation.model_definition.id_field_name)();
+
+    let mut distinct_keys = keys.iter().flatten().cloned().collect::<Vec<_>>();
+    distinct_keys.sort();
+    distinct_keys.dedup();
+
+    // ids are bound as text and cast back so that the lookup can use the index of the column
+    let keys_param = format!("$1::text[]::{}[]", key_type.sql_type());
+
+    let soft_delete_condition = match relation.model_definition.soft_delete_column() {
+        Some(column) => format!("AND r.{} IS NULL", column),
+        None => "".into(),
+    };
+
+    let statement = match &relation.reference {
+        Reference::From(_) => format!(
+            "SELECT r.{}::text AS _key, to_jsonb(r.*) AS _row FROM {} AS r WHERE r.{} = ANY({}) {} ORDER BY r.{}",
+            related_id, related_table, related_id, keys_param, soft_delete_condition, related_id
+        ),
+        Reference::To(column) => format!(
+            "SELECT r.{}::text AS _key, to_jsonb(r.*) AS _row FROM {} AS r WHERE r.{} = ANY({}) {} ORDER BY r.{}",
+            column, related_table, column, keys_param, soft_delete_condition, related_id
+        ),
+        Reference::Via((junction_table, from_reference, to_reference)) => format!(
+            "SELECT j.{}::text AS _key, to_jsonb(r.*) AS _row FROM {} AS j JOIN {} AS r ON r.{} = j.{} WHERE j.{} = ANY({}) {} ORDER BY r.{}",
+            from_reference,
+            junction_table,
+            related_table,
+            related_id,
+            to_reference,
+            from_reference,
+            keys_param,
+            soft_delete_condition,
+            related_id
+        ),
+    };
+
+    tracing::info!("{}", statement);
+
+    let rows: Vec<(String, Value)> = sqlx::query_as(&statement)
+        .bind(distinct_keys)
+        .fetch_all(&mut *executor)
+        .await?;
+
+    let (related_keys, mut related): (Vec<_>, Vec<_>) = rows.into_iter().unzip();
+
+    if !subtree.is_empty() {
+        load_children(executor, &relation.model_definition, &mut related, subtree).await?;
+    }
+
+    let mut grouped: HashMap<String, Vec<Value>> = HashMap::new();
+
+    for (key, row) in related_keys.into_iter().zip(related) {
+        grouped.entry(key).or_default().push(row);
+    }
+
+    let values = keys
+        .into_iter()
+        .map(|key| {
+            let rows = key.and_then(|key| grouped.get(&key).cloned());
+
+            match &relation.reference {
+                Reference::From(_) => rows
+                    .and_then(|rows| rows.into_iter().next())
+                    .unwrap_or(Value::Null),
+                _ => Value::Array(rows.unwrap_or_default()),
+            }
+        })
+        .collect();
+
+    Ok(values)
+}
+
+fn find_relation<'a>(
+    relation_defs: &'a [RelationDef],
+    name: &str,
+) -> Result<&'a RelationDef, Error> {
+    relation_defs
+        .iter()
+        .find(|def| def.name == name)
+        .ok_or_else(|| Error::bad_request(&format!("invalid include: undefined relation {}", name)))
+}
+
+/// The column of the parent holding the key that the related rows are looked up by
+fn key_column(model_def: &ModelDef, relation: &RelationDef) -> Result<(String, FieldType), Error> {
+    let column = match &relation.reference {
+        Reference::From(column) => column.clone(),
+        Reference::To(_) | Reference::Via(_) => (model_def.id_field_name)(),
+    };
+
+    let def = (model_def.field_definitions)()
+        .into_iter()
+        .find(|def| def.name == column)
+        .ok_or_else(|| Error::internal(&format!("undefined key column {}", column)))?;
+
+    Ok((column, def.type_))
+}
+
+fn node_keys<T: Model>(nodes: &[&T], column: &str) -> Result<Vec<Option<String>>, Error> {
+    nodes
+        .iter()
+        .map(|node| {
+            let value = serde_json::to_value(node.field_value(column)?)
+                .map_err(|_| Error::internal("unable to serialize key column"))?;
+
+            Ok(json_key(&value))
+        })
+        .collect()
+}
+
+fn json_key(value: &Value) -> Option<String> {
+    match value {
+        Value::String(key) => Some(key.clone()),
+        Value::Number(key) => Some(key.to_string()),
+        _ => None,
+    }
+}
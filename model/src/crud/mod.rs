@@ -1,27 +1,51 @@
+mod aggregate;
+mod batches;
 mod bulk_create;
 mod bulk_create_association;
+mod bulk_upsert;
 mod create;
 mod create_association;
 mod delete;
 mod delete_association;
+mod delete_where;
+mod facets;
+mod include;
+mod patch;
+mod restore;
 mod select;
+mod tree;
 mod update;
+mod update_where;
 mod upsert;
 mod util;
 
+use aggregate::Aggregation;
 use async_trait::async_trait;
 use bulk_create::BulkCreate;
 use bulk_create_association::BulkCreateAssociation;
+use bulk_upsert::BulkUpsert;
 use create_association::CreateAssociation;
 use delete_association::DeleteAssociation;
+use delete_where::DeleteWhere;
 use sqlx::{Database, FromRow, Postgres};
+use update_where::UpdateWhere;
 use upsert::Upsert;
-use uuid::Uuid;
+
+pub use bulk_upsert::UpsertCounts;
+pub use facets::Facet;
+pub(crate) use select::Select;
+pub use upsert::UpsertOutcome;
 
 use crate::Error;
-use crate::{Model, Query};
+use crate::{FieldValue, Filter, Model, ModelPatch, Query};
 
-use self::{create::Create, delete::Delete, select::Select, update::Update};
+use self::{
+    create::{Create, CreateIfAbsent},
+    delete::Delete,
+    patch::Patch,
+    restore::Restore,
+    update::Update,
+};
 
 #[async_trait]
 pub trait Crud
@@ -38,6 +62,11 @@ where
         Create::new(self)
     }
 
+    /// Like `create`, but does nothing if the row conflicts with an existing one
+    fn create_if_absent<'a>(&'a self) -> CreateIfAbsent<'a, Self> {
+        CreateIfAbsent::new(self)
+    }
+
     fn upsert<'a>(&'a mut self) -> Upsert<'a, Self> {
         Upsert::new(self)
     }
@@ -46,28 +75,56 @@ where
         Update::new(self)
     }
 
-    fn delete<'a>(&'a self) -> Delete<Self> {
+    /// Updates every row matched by the filter in a single statement, e.g.
+    /// `Cake::update_where(filter).set("archived", true).execute(&mut tx)`
+    fn update_where(filter: Filter) -> UpdateWhere<Self> {
+        UpdateWhere::new(filter)
+    }
+
+    /// Updates only the fields set on the patch of the row with the given id, see `ModelPatch`
+    fn patch<Id: Into<Self::Id>, P: ModelPatch<Model = Self>>(id: Id, patch: P) -> Patch<Self, P> {
+        Patch::new(id.into(), patch)
+    }
+
+    /// Deletes the row, or marks it as deleted if the model has a `soft_delete` field
+    fn delete(&self) -> Delete<Self> {
         Delete::new(self.id_field_value())
     }
 
-    fn delete_by_id<Id: Into<Uuid>>(id: Id) -> Delete<Self> {
+    /// Deletes the row even if the model has a `soft_delete` field
+    fn hard_delete(&self) -> Delete<Self> {
+        Delete::new(self.id_field_value()).hard()
+    }
+
+    /// Undoes the soft delete of the row
+    fn restore(&self) -> Restore<Self> {
+        Restore::new(self.id_field_value())
+    }
+
+    fn delete_by_id<Id: Into<Self::Id>>(id: Id) -> Delete<Self> {
         Delete::new(id.into())
     }
 
+    /// Deletes every row matched by the filter in a single statement. An empty filter is
+    /// refused unless `all` is called
+    fn delete_where(filter: Filter) -> DeleteWhere<Self> {
+        DeleteWhere::new(filter)
+    }
+
     fn create_association<'a>(
         &'a self,
         relation_name: &'a str,
-        associated_id: &'a Uuid,
+        associated_id: impl Into<FieldValue>,
     ) -> CreateAssociation<'a, Self> {
-        CreateAssociation::new(self, relation_name, associated_id)
+        CreateAssociation::new(self, relation_name, associated_id.into())
     }
 
     fn delete_association<'a>(
         &'a self,
         relation_name: &'a str,
-        associated_id: &'a Uuid,
+        associated_id: impl Into<FieldValue>,
     ) -> DeleteAssociation<'a, Self> {
-        DeleteAssociation::new(self, relation_name, associated_id)
+        DeleteAssociation::new(self, relation_name, associated_id.into())
     }
 
     fn bulk_create<'a, I>(iter: I) -> BulkCreate<'a, Self>
@@ -77,12 +134,23 @@ where
         BulkCreate::new(iter)
     }
 
-    fn bulk_create_association<'a, I>(
+    /// Inserts every row or updates the existing one, see `upsert`. Meant for large batches,
+    /// since the rows are copied to the database before a single statement upserts them
+    fn bulk_upsert<'a, I>(iter: I) -> BulkUpsert<'a, Self>
+    where
+        I: Iterator<Item = Self> + 'a,
+    {
+        BulkUpsert::new(iter)
+    }
+
+    /// Associates each pair of (id, associated id) through the junction table of the relation
+    fn bulk_create_association<'a, I, A>(
         relation_name: &'a str,
         iter: I,
     ) -> BulkCreateAssociation<'a, Self>
     where
-        I: Iterator<Item = (Uuid, Uuid)> + 'a,
+        I: Iterator<Item = (Self::Id, A)> + 'a,
+        A: Into<FieldValue>,
     {
         BulkCreateAssociation::new(relation_name, iter)
     }
@@ -91,6 +159,10 @@ where
         Select::new()
     }
 
+    fn aggregate() -> Aggregation<Self> {
+        Aggregation::new(Select::new())
+    }
+
     fn select_from_query(query: Query<Self>) -> Result<Select<Self>, Error> {
         query.try_into()
     }
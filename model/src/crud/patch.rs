@@ -0,0 +1,131 @@
+use sqlx::{postgres::PgRow, FromRow, PgConnection};
+
+use crate::Error;
+use crate::{FieldValue, Model, ModelPatch};
+
+use super::select::Select;
+use super::update::version_conflict;
+use super::util::{build_query_as, inline_bindings, version_increment};
+
+#[derive(Debug)]
+pub struct Patch<T: Model, P> {
+    id: T::Id,
+    patch: P,
+}
+
+impl<T, P> Patch<T, P>
+where
+    T: Model + for<'b> FromRow<'b, PgRow> + Unpin + Sized + Send,
+    P: ModelPatch<Model = T>,
+{
+    pub(crate) fn new(id: T::Id, patch: P) -> Self {
+        Self { id, patch }
+    }
+
+    /// Updates the fields set on the patch and returns the updated row.
+    /// If no field is set the row is returned unchanged. Setting the version field of the model
+    /// makes the update fail with `Error::Conflict` unless it matches the stored version
+    pub async fn execute(self, executor: &mut PgConnection) -> Result<T, Error> {
+        let Some((statement, var_bindings)) = self.to_sql()? else {
+            return Select::<T>::new().by_id(self.id).fetch_one(executor).await;
+        };
+
+        let patched = build_query_as(&statement, var_bindings)
+            .fetch_optional(executor)
+            .await?;
+
+        match patched {
+            Some(patched) => Ok(patched),
+            None if self.expects_version()? => Err(version_conflict()),
+            None => Err(sqlx::Error::RowNotFound.into()),
+        }
+    }
+
+    /// Renders the statement along with its bindings, None if no field is set on the patch
+    pub fn to_sql(&self) -> Result<Option<(String, Vec<FieldValue>)>, Error> {
+        let table_name = T::table_name();
+        let id_field_name = T::id_field_name();
+        let defs = T::field_definitions();
+
+        let fields = self.patch.fields()?;
+
+        if fields.is_empty() {
+            return Ok(None);
+        }
+
+        let mut var_bindings = vec![self.id.clone().into()];
+        let mut set_clauses = vec![];
+        let mut where_clause = format!("{} = $1", id_field_name);
+
+        if let Some(column) = T::definition().soft_delete_column() {
+            where_clause = format!("{} AND {} IS NULL", where_clause, column);
+        }
+
+        if let Some(def) = defs.iter().find(|def| def.version) {
+            set_clauses.push(format!(
+                "{} = {}",
+                def.name,
+                version_increment(def, &def.name)
+            ));
+        }
+
+        if let Some(def) = defs.iter().find(|def| def.updated_at && !def.version) {
+            set_clauses.push(format!("{} = now()", def.name));
+        }
+
+        for (name, value) in fields.into_iter() {
+            let def = defs.iter().find(|def| def.name == name).ok_or_else(|| {
+                Error::bad_request(&format!("invalid patch: unknown field {}", name))
+            })?;
+
+            // the version is compared instead of written
+            if def.version {
+                var_bindings.push(value);
+                where_clause = format!("{} AND {} = ${}", where_clause, name, var_bindings.len());
+                continue;
+            }
+
+            if def.immutable || def.read_only || def.updated_at || def.soft_delete {
+                return Err(Error::bad_request(&format!(
+                    "invalid patch: {} can't be changed",
+                    name
+                )));
+            }
+
+            var_bindings.push(value);
+            set_clauses.push(format!("{} = ${}", name, var_bindings.len()));
+        }
+
+        let statement = format!(
+            "UPDATE {} SET {} WHERE {} RETURNING *",
+            table_name,
+            set_clauses.join(", "),
+            where_clause
+        );
+
+        Ok(Some((statement, var_bindings)))
+    }
+
+    fn expects_version(&self) -> Result<bool, Error> {
+        let Some(def) = T::field_definitions().into_iter().find(|def| def.version) else {
+            return Ok(false);
+        };
+
+        let expects_version = self
+            .patch
+            .fields()?
+            .iter()
+            .any(|(name, _)| name == &def.name);
+
+        Ok(expects_version)
+    }
+
+    /// Renders the statement with its bindings inlined, e.g. to paste it into psql
+    pub fn to_pretty_sql(&self) -> Result<Option<String>, Error> {
+        let statement = self
+            .to_sql()?
+            .map(|(statement, var_bindings)| inline_bindings(&statement, &var_bindings));
+
+        Ok(statement)
+    }
+}
@@ -0,0 +1,82 @@
+use std::marker::PhantomData;
+
+use sqlx::{postgres::PgRow, FromRow, PgConnection};
+
+use crate::Error;
+use crate::{
+    crud::util::{build_query, inline_bindings, touch_clauses},
+    FieldValue, Model,
+};
+
+#[derive(Clone, Debug)]
+pub struct Restore<T: Model> {
+    id: T::Id,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Restore<T>
+where
+    T: Model + for<'b> FromRow<'b, PgRow> + Unpin + Sized + Send,
+{
+    pub(crate) fn new(id: T::Id) -> Self {
+        Self {
+            id,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Undoes the soft delete of the row. Restoring a row that isn't deleted does nothing,
+    /// while a row that doesn't exist fails with `Error::NotFound`
+    pub async fn execute(self, executor: &mut PgConnection) -> Result<(), Error> {
+        let (statement, var_bindings) = self.to_sql()?;
+
+        let result = build_query(&statement, var_bindings.clone())
+            .execute(&mut *executor)
+            .await?;
+
+        if result.rows_affected() > 0 {
+            return Ok(());
+        }
+
+        // only deleted rows are updated, so tell a row that isn't deleted from a missing one
+        let statement = format!(
+            "SELECT 1 FROM {} WHERE {} = $1",
+            T::table_name(),
+            T::id_field_name()
+        );
+
+        build_query(&statement, var_bindings)
+            .fetch_one(executor)
+            .await?;
+
+        Ok(())
+    }
+
+    pub fn to_sql(&self) -> Result<(String, Vec<FieldValue>), Error> {
+        let table_name = T::table_name();
+        let id_field_name = T::id_field_name();
+
+        let column = T::definition().soft_delete_column().ok_or_else(|| {
+            Error::bad_request("invalid restore: the model doesn't have a soft_delete field")
+        })?;
+
+        let mut set_clauses = vec![format!("{} = NULL", column)];
+        set_clauses.extend(touch_clauses(&T::field_definitions()));
+
+        let statement = format!(
+            "UPDATE {} SET {} WHERE {} = $1 AND {} IS NOT NULL",
+            table_name,
+            set_clauses.join(", "),
+            id_field_name,
+            column
+        );
+
+        Ok((statement, vec![self.id.clone().into()]))
+    }
+
+    /// Renders the statement with its bindings inlined, e.g. to paste it into psql
+    pub fn to_pretty_sql(&self) -> Result<String, Error> {
+        let (statement, var_bindings) = self.to_sql()?;
+        Ok(inline_bindings(&statement, &var_bindings))
+    }
+}
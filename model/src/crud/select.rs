@@ -1,44 +1,98 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
 use std::marker::PhantomData;
 
-use sqlx::{postgres::PgRow, FromRow, PgConnection};
+use async_stream::try_stream;
+use futures::{Stream, TryStreamExt};
+use sqlx::{postgres::PgRow, types::Json, FromRow, PgConnection, Row};
 
-use crate::filter::ast::Var;
+use crate::filter::ast::{Aggregate, CompOp, LogicOp, Subquery, Var};
+use crate::filter::util::shift_placeholders;
+use crate::model::{FieldDefinition, FieldType};
 use crate::{
-    filter::ast::Expr, Connection, Cursor, FieldValue, Filter, Model, PageInfo, Query,
-    SortDirection,
+    filter::ast::Expr, Connection, Cursor, Edge, EdgeConnection, FieldValue, Filter, Loaded, Model,
+    ModelId, PageInfo, Query, SortDirection,
 };
 use crate::{Error, ModelDef};
 
-use super::util::build_query_as;
+use super::batches::Batches;
+use super::facets::Facets;
+use super::include::{load_relations, IncludeTree, LimitedInclude};
+use super::tree::{Traversal, TraversalDirection};
+use super::util::{build_query, build_query_as, decode_column, inline_bindings};
 
 const DEFAULT_LIMIT: i64 = 100;
+const DEFAULT_MAX_OFFSET: i64 = 10_000;
 
 #[derive(FromRow)]
 pub struct WithCursor<T> {
     #[sqlx(flatten)]
     node: T,
     _cursor: Option<String>,
+    _cursor_id: String,
     _next_page: bool,
 }
 
 #[derive(Clone, Debug)]
 pub struct Select<T: Model> {
     select_path: String,
+    columns: Option<Vec<String>>,
+    includes: IncludeTree,
+    limited_includes: Vec<LimitedInclude>,
     filters: Vec<Filter>,
     order_by: OrderBy,
-    cursor: Option<Cursor>,
+    pub(crate) cursor: Option<Cursor>,
     pub(crate) limit: Option<i64>,
-    for_update: bool,
+    pub(crate) offset: Option<i64>,
+    /// the 1-based page of offset pagination, the offset is computed once the query is prepared
+    page: Option<i64>,
+    max_offset: i64,
+    pub(crate) include_prev_page: bool,
+    lock: Option<RowLock>,
+    lock_wait: LockWait,
+    #[allow(clippy::type_complexity)]
+    set_operation: Option<Box<(Select<T>, SetOperator, Select<T>)>>,
+    traversal: Option<Traversal>,
+    deleted: DeletedRows,
     _marker: PhantomData<T>,
 }
 
+/// Which rows of a model with a `soft_delete` field are selected
+#[derive(Clone, Debug, PartialEq)]
+enum DeletedRows {
+    Exclude,
+    Include,
+    Only,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum SetOperator {
+    Union,
+    Intersect,
+    Except,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum RowLock {
+    Update,
+    NoKeyUpdate,
+    Share,
+    KeyShare,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum LockWait {
+    Wait,
+    NoWait,
+    SkipLocked,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum OrderBy {
     IdAsc,
     IdDesc,
-    SecondaryAsc(Var),
-    SecondaryDesc(Var),
+    SecondaryAsc(Var, Option<Aggregate>),
+    SecondaryDesc(Var, Option<Aggregate>),
 }
 
 impl OrderBy {
@@ -46,38 +100,34 @@ impl OrderBy {
         match self {
             OrderBy::IdAsc => Self::IdDesc,
             OrderBy::IdDesc => Self::IdAsc,
-            OrderBy::SecondaryAsc(field) => Self::SecondaryDesc(field.clone()),
-            OrderBy::SecondaryDesc(field) => Self::SecondaryAsc(field.clone()),
+            OrderBy::SecondaryAsc(field, aggregate) => {
+                Self::SecondaryDesc(field.clone(), aggregate.clone())
+            }
+            OrderBy::SecondaryDesc(field, aggregate) => {
+                Self::SecondaryAsc(field.clone(), aggregate.clone())
+            }
         }
     }
 
     fn selects<T: Model>(&self) -> String {
         let primary_field_reference = self.primary_field_reference::<T>();
+        let id_reference = format!("{}.{}", T::table_name(), T::id_field_name());
+
         match &self {
             OrderBy::IdAsc | OrderBy::IdDesc => {
                 format!(
-                    "{}::text AS _cursor, {} AS _order_by_primary",
-                    primary_field_reference, primary_field_reference
+                    "{}::text AS _cursor, {}::text AS _cursor_id, {} AS _order_by_primary",
+                    primary_field_reference, id_reference, primary_field_reference
                 )
             }
-            OrderBy::SecondaryAsc(var) | OrderBy::SecondaryDesc(var) => {
-                if matches!(var, Var::Node(_)) {
-                    format!(
-                        "MAX({})::text AS _cursor, MAX({}) AS _order_by_primary, {}.{} AS _order_by_secondary",
-                        primary_field_reference,
-                        primary_field_reference,
-                        T::table_name(),
-                        T::id_field_name()
-                    )
-                } else {
-                    format!(
-                        "{}::text AS _cursor, {} AS _order_by_primary, {}.{} AS _order_by_secondary",
-                        primary_field_reference,
-                        primary_field_reference,
-                        T::table_name(),
-                        T::id_field_name()
-                    )
-                }
+            OrderBy::SecondaryAsc(_, _) | OrderBy::SecondaryDesc(_, _) => {
+                format!(
+                    "{}::text AS _cursor, {}::text AS _cursor_id, {} AS _order_by_primary, {} AS _order_by_secondary",
+                    primary_field_reference,
+                    id_reference,
+                    primary_field_reference,
+                    id_reference
+                )
             }
         }
     }
@@ -85,9 +135,11 @@ impl OrderBy {
     fn to_sql<T: Model>(&self) -> String {
         match &self {
             OrderBy::IdAsc => "_order_by_primary ASC".into(),
-            OrderBy::SecondaryAsc(_) => "_order_by_primary ASC, _order_by_secondary ASC".into(),
+            OrderBy::SecondaryAsc(_, _) => "_order_by_primary ASC, _order_by_secondary ASC".into(),
             OrderBy::IdDesc => "_order_by_primary DESC".into(),
-            OrderBy::SecondaryDesc(_) => "_order_by_primary DESC, _order_by_secondary DESC".into(),
+            OrderBy::SecondaryDesc(_, _) => {
+                "_order_by_primary DESC, _order_by_secondary DESC".into()
+            }
         }
     }
 
@@ -96,27 +148,85 @@ impl OrderBy {
             OrderBy::IdAsc | OrderBy::IdDesc => {
                 format!("{}.{}", T::table_name(), T::id_field_name())
             }
-            OrderBy::SecondaryAsc(var) | OrderBy::SecondaryDesc(var) => {
+            OrderBy::SecondaryAsc(var, aggregate) | OrderBy::SecondaryDesc(var, aggregate) => {
                 let mut reference = var.to_sql();
 
                 if matches!(var, Var::Leaf(_)) {
                     reference = format!("{}.{}", T::table_name(), reference);
                 }
 
-                reference
+                match aggregate {
+                    Some(aggregate) => aggregate.to_sql(&reference),
+                    None => reference,
+                }
             }
         }
     }
 
-    fn references_relation(&self) -> bool {
-        if matches!(self, OrderBy::SecondaryAsc(Var::Node(_)))
-            || matches!(self, OrderBy::SecondaryDesc(Var::Node(_)))
-        {
-            true
-        } else {
-            false
+    fn aggregate(&self) -> Option<&Aggregate> {
+        match self {
+            OrderBy::SecondaryAsc(_, aggregate) | OrderBy::SecondaryDesc(_, aggregate) => {
+                aggregate.as_ref()
+            }
+            _ => None,
         }
     }
+
+    /// The type of the value held by cursors for this ordering
+    fn cursor_value_type<T: Model>(&self) -> Result<Option<FieldType>, Error> {
+        match self {
+            OrderBy::IdAsc | OrderBy::IdDesc => Ok(None),
+            OrderBy::SecondaryAsc(var, aggregate) | OrderBy::SecondaryDesc(var, aggregate) => {
+                let def = var.resolve_definition(&T::definition())?;
+
+                let type_ = match aggregate {
+                    Some(aggregate) => aggregate.result_type(&def.type_)?,
+                    None => def.type_,
+                };
+
+                Ok(type_.into())
+            }
+        }
+    }
+
+    fn references_relation(&self) -> bool {
+        matches!(
+            self,
+            OrderBy::SecondaryAsc(Var::Node(_), _) | OrderBy::SecondaryDesc(Var::Node(_), _)
+        )
+    }
+}
+
+impl Display for SetOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            Self::Union => "UNION",
+            Self::Intersect => "INTERSECT",
+            Self::Except => "EXCEPT",
+        };
+
+        f.write_str(op)
+    }
+}
+
+impl RowLock {
+    /// Only the rows of the root table are locked, postgres can't lock the nullable side of the relation joins
+    fn to_sql(&self, table_name: &str, wait: &LockWait) -> String {
+        let strength = match self {
+            RowLock::Update => "FOR UPDATE",
+            RowLock::NoKeyUpdate => "FOR NO KEY UPDATE",
+            RowLock::Share => "FOR SHARE",
+            RowLock::KeyShare => "FOR KEY SHARE",
+        };
+
+        let wait = match wait {
+            LockWait::Wait => "",
+            LockWait::NoWait => " NOWAIT",
+            LockWait::SkipLocked => " SKIP LOCKED",
+        };
+
+        format!("{} OF {}{}", strength, table_name, wait)
+    }
 }
 
 impl<T: Model> Select<T> {
@@ -130,6 +240,8 @@ impl<T: Model> Select<T> {
 
         self.filters.extend(other.filters);
         self.limit = other.limit;
+        self.offset = other.offset;
+        self.page = other.page;
         self.order_by = other.order_by;
         self.cursor = other.cursor;
 
@@ -155,10 +267,197 @@ impl<T: Model> Select<T> {
         self
     }
 
+    /// Only selects the given columns instead of every column of the table.
+    /// Use `fetch_page_as` or `fetch_page_values` to decode the partial rows
+    pub fn columns(mut self, columns: &[&str]) -> Self {
+        self.columns = columns
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .into();
+        self
+    }
+
+    /// Only selects a single column, e.g. for `Filter::in_select`
+    pub fn column(self, name: &str) -> Self {
+        self.columns(&[name])
+    }
+
+    /// Combines the rows of both selects, removing duplicates. The ordering, limit and cursor of the
+    /// operands are ignored, while filters added afterwards apply to the combined rows, which
+    /// are sorted and paged like the rows of a table
+    pub fn union(self, other: Select<T>) -> Self {
+        self.set_operation(SetOperator::Union, other)
+    }
+
+    /// Only keeps the rows matched by both selects, see `union`
+    pub fn intersect(self, other: Select<T>) -> Self {
+        self.set_operation(SetOperator::Intersect, other)
+    }
+
+    /// Removes the rows matched by `other`, see `union`
+    pub fn except(self, other: Select<T>) -> Self {
+        self.set_operation(SetOperator::Except, other)
+    }
+
+    /// Only selects the rows below the given row, following a `belongs_to` relation of the model
+    /// to itself like `parent`. Filters, sorting and pagination apply to the descendants, use
+    /// `fetch_all_with_depth` to also receive how many levels below the row each one is
+    pub fn descendants_of(self, relation: &str, id: impl Into<FieldValue>) -> Self {
+        self.traverse(relation, id.into(), TraversalDirection::Descendants)
+    }
+
+    /// Only selects the rows above the given row, following a `belongs_to` relation of the model
+    /// to itself like `parent`, see `descendants_of`
+    pub fn ancestors_of(self, relation: &str, id: impl Into<FieldValue>) -> Self {
+        self.traverse(relation, id.into(), TraversalDirection::Ancestors)
+    }
+
+    /// Limits `descendants_of` and `ancestors_of` to the given number of levels
+    pub fn max_depth(mut self, max_depth: i64) -> Self {
+        if let Some(traversal) = self.traversal.as_mut() {
+            traversal.max_depth = max_depth.into();
+        }
+
+        self
+    }
+
+    /// Also selects soft deleted rows, which are excluded by default. Rows of joined relations
+    /// are still excluded if deleted
+    pub fn with_deleted(mut self) -> Self {
+        self.deleted = DeletedRows::Include;
+        self
+    }
+
+    /// Only selects soft deleted rows, e.g. to list the rows that can be restored
+    pub fn only_deleted(mut self) -> Self {
+        self.deleted = DeletedRows::Only;
+        self
+    }
+
+    /// Eagerly loads a relation, or a nested relation path like `toppings.supplier`, of the fetched rows.
+    /// Use `fetch_page_loaded` or `fetch_all_loaded` to receive them
+    pub fn include(mut self, path: &str) -> Self {
+        self.includes.insert(path);
+        self
+    }
+
+    /// Eagerly loads the first `limit` rows of a has_many relation per fetched row, ordered by a field of the relation.
+    /// Use `fetch_page_loaded` or `fetch_all_loaded` to receive them
+    pub fn include_limited(
+        mut self,
+        name: &str,
+        sort_by: &str,
+        direction: SortDirection,
+        limit: i64,
+    ) -> Self {
+        self.limited_includes.push(LimitedInclude {
+            name: name.into(),
+            sort_by: sort_by.into(),
+            direction,
+            limit,
+        });
+        self
+    }
+
+    /// Locks the selected rows of the table with `FOR UPDATE`. Rows of joined relations are never locked
     pub fn for_update(mut self) -> Self {
-        self.for_update = true;
+        self.lock = RowLock::Update.into();
+        self
+    }
+
+    pub fn for_no_key_update(mut self) -> Self {
+        self.lock = RowLock::NoKeyUpdate.into();
+        self
+    }
+
+    pub fn for_share(mut self) -> Self {
+        self.lock = RowLock::Share.into();
+        self
+    }
+
+    pub fn for_key_share(mut self) -> Self {
+        self.lock = RowLock::KeyShare.into();
+        self
+    }
+
+    /// Skips rows locked by other transactions instead of waiting for them, e.g. to consume a work queue
+    pub fn skip_locked(mut self) -> Self {
+        self.lock_wait = LockWait::SkipLocked;
+        self
+    }
+
+    /// Fails instead of waiting when a row is locked by another transaction
+    pub fn nowait(mut self) -> Self {
+        self.lock_wait = LockWait::NoWait;
+        self
+    }
+
+    /// Switches to offset pagination, skipping the first `offset` rows.
+    /// The resulting page info contains page numbers and the total count instead of cursors
+    pub fn offset(mut self, offset: i64) -> Self {
+        self.offset = offset.into();
+        self.page = None;
+        self
+    }
+
+    /// Switches to offset pagination, fetching the 1-based `page` of `per_page` rows.
+    /// Preparing or fetching the query fails with `Error::BadRequest` if either is below 1 or
+    /// the page is out of range
+    pub fn page(mut self, page: i64, per_page: i64) -> Self {
+        self.offset = None;
+        self.page = page.into();
+        self.limit = per_page.into();
+        self
+    }
+
+    /// The largest offset accepted in offset pagination, defaults to 10,000.
+    /// Deep offsets force the database to scan every skipped row, so keep this bounded.
+    /// Every query rendering a larger offset fails with `Error::BadRequest`
+    pub fn max_offset(mut self, max_offset: i64) -> Self {
+        self.max_offset = max_offset;
         self
     }
+
+    /// Counts the matching rows per value of each of the given enum or bool fields
+    pub fn facets(self, fields: &[&str]) -> Facets<T> {
+        Facets::new(self, fields.iter().map(|f| f.to_string()).collect())
+    }
+
+    /// Renders the statement fetching the current page, along with its bindings
+    pub fn to_sql(&self) -> Result<(String, Vec<FieldValue>), Error> {
+        let filters = self.build_filters()?;
+        self.prepare(filters)
+    }
+
+    /// Renders the statement with its bindings inlined, e.g. to paste it into psql
+    pub fn to_pretty_sql(&self) -> Result<String, Error> {
+        let (statement, var_bindings) = self.to_sql()?;
+        Ok(inline_bindings(&statement, &var_bindings))
+    }
+
+    /// Runs the statement with `EXPLAIN (ANALYZE, FORMAT JSON)` and returns the plan.
+    /// The statement is actually executed, including any row locks
+    pub async fn explain(&self, executor: &mut PgConnection) -> Result<serde_json::Value, Error> {
+        let (statement, var_bindings) = self.to_sql()?;
+        let statement = format!("EXPLAIN (ANALYZE, FORMAT JSON) {}", statement);
+
+        let (plan,) = build_query_as::<(Json<serde_json::Value>,)>(&statement, var_bindings)
+            .fetch_one(executor)
+            .await?;
+
+        Ok(plan.0)
+    }
+
+    /// Walks the result set in batches of `size` rows using the keyset cursor, so
+    /// rows inserted while iterating don't cause already visited rows to be skipped or repeated
+    pub fn batches(mut self, size: i64) -> Batches<T> {
+        self.limit = size.into();
+        self.cursor = None;
+        self.include_prev_page = false;
+
+        Batches::new(self)
+    }
 }
 
 impl<T> Select<T>
@@ -179,20 +478,262 @@ where
         Ok(nodes.into_iter().map(|node| node.node).collect())
     }
 
-    pub async fn fetch_page(mut self, executor: &mut PgConnection) -> Result<Connection<T>, Error> {
+    /// Fetches every row of `descendants_of` or `ancestors_of` along with its depth, which
+    /// counts from 1 for the children or the parent of the row the traversal started from
+    pub async fn fetch_all_with_depth(
+        mut self,
+        executor: &mut PgConnection,
+    ) -> Result<Vec<(T, i32)>, Error> {
+        if self.traversal.is_none() || self.columns.is_some() {
+            return Err(Error::bad_request(
+                "fetching the depth requires descendants_of or ancestors_of without columns",
+            ));
+        }
+
+        self.limit = None;
+        self.cursor = None;
+
+        let filters = self.build_filters()?;
+        let (statement, var_bindings) = self.prepare(filters)?;
+
+        let rows = build_query(&statement, var_bindings)
+            .fetch_all(executor)
+            .await?;
+
+        rows.iter()
+            .map(|row| {
+                let node = T::from_row(row)?;
+                let depth: i32 = row.try_get("_depth")?;
+
+                Ok((node, depth))
+            })
+            .collect()
+    }
+
+    /// Streams the rows of the result set one by one instead of collecting them into memory
+    pub fn fetch_stream<'e>(
+        mut self,
+        executor: &'e mut PgConnection,
+    ) -> impl Stream<Item = Result<T, Error>> + 'e
+    where
+        T: 'e,
+    {
+        self.limit = None;
+        self.cursor = None;
+
+        try_stream! {
+            let filters = self.build_filters()?;
+            let (statement, var_bindings) = self.prepare(filters)?;
+
+            let mut rows = build_query_as::<WithCursor<T>>(&statement, var_bindings).fetch(executor);
+
+            while let Some(row) = rows.try_next().await? {
+                yield row.node;
+            }
+        }
+    }
+
+    pub async fn fetch_page(self, executor: &mut PgConnection) -> Result<Connection<T>, Error> {
+        self.fetch_page_as::<T>(executor).await
+    }
+
+    /// Fetches a page with a cursor for every node, e.g. for GraphQL clients resuming from any item
+    pub async fn fetch_edges(
+        self,
+        executor: &mut PgConnection,
+    ) -> Result<EdgeConnection<T>, Error> {
+        self.fetch_page_with(executor, |row| Ok(WithCursor::<T>::from_row(row)?))
+            .await
+    }
+
+    /// Fetches a page along with the relations requested through `include` and `include_limited`.
+    /// Related rows are loaded with one query per relation, pagination only applies to the root rows
+    pub async fn fetch_page_loaded(
+        self,
+        executor: &mut PgConnection,
+    ) -> Result<Connection<Loaded<T>>, Error> {
+        let includes = self.includes.clone();
+        let limited_includes = self.limited_includes.clone();
+        let connection = self.fetch_page(&mut *executor).await?;
+
+        let nodes = connection.nodes.iter().collect::<Vec<_>>();
+        let mut relations = load_relations(executor, &nodes, &includes, &limited_includes)
+            .await?
+            .into_iter();
+
+        Ok(connection.map(|node| Loaded {
+            node,
+            relations: relations.next().unwrap_or_default(),
+        }))
+    }
+
+    pub async fn fetch_all_loaded(
+        self,
+        executor: &mut PgConnection,
+    ) -> Result<Vec<Loaded<T>>, Error> {
+        let includes = self.includes.clone();
+        let limited_includes = self.limited_includes.clone();
+        let nodes = self.fetch_all(&mut *executor).await?;
+
+        let relations = load_relations(
+            executor,
+            &nodes.iter().collect::<Vec<_>>(),
+            &includes,
+            &limited_includes,
+        )
+        .await?;
+
+        Ok(nodes
+            .into_iter()
+            .zip(relations)
+            .map(|(node, relations)| Loaded { node, relations })
+            .collect())
+    }
+
+    /// Fetches a page decoded into a projection type, usually combined with `columns`
+    pub async fn fetch_page_as<P>(self, executor: &mut PgConnection) -> Result<Connection<P>, Error>
+    where
+        P: for<'a> FromRow<'a, PgRow>,
+    {
+        self.fetch_page_with(executor, |row| Ok(WithCursor::<P>::from_row(row)?))
+            .await
+            .map(Connection::from)
+    }
+
+    /// Fetches a page with every node decoded into a map of the selected column values
+    pub async fn fetch_page_values(
+        self,
+        executor: &mut PgConnection,
+    ) -> Result<Connection<HashMap<String, FieldValue>>, Error> {
+        let defs = self.selected_definitions()?;
+
+        self.fetch_page_with(executor, |row| {
+            let mut node = HashMap::new();
+
+            for def in defs.iter() {
+                node.insert(def.name.clone(), decode_column(row, &def.name, &def.type_)?);
+            }
+
+            Ok(WithCursor {
+                node,
+                _cursor: row.try_get("_cursor")?,
+                _cursor_id: row.try_get("_cursor_id")?,
+                _next_page: row.try_get("_next_page")?,
+            })
+        })
+        .await
+        .map(Connection::from)
+    }
+
+    async fn fetch_page_with<N>(
+        mut self,
+        executor: &mut PgConnection,
+        decode: impl Fn(&PgRow) -> Result<WithCursor<N>, Error>,
+    ) -> Result<EdgeConnection<N>, Error> {
+        if self.is_offset_paginated() && self.limit.is_some_and(|limit| limit < 1) {
+            return Err(Error::bad_request("per_page must be 1 or greater"));
+        }
+
         self.limit = match self.limit {
             Some(limit) if limit > 0 => limit.into(),
             _ => DEFAULT_LIMIT.into(),
         };
 
+        if self.is_offset_paginated() {
+            return self.fetch_offset_page(executor, decode).await;
+        }
+
         let filters = self.build_filters()?;
         let (statement, var_bindings) = self.prepare(filters)?;
 
-        let nodes = build_query_as::<WithCursor<T>>(&statement, var_bindings)
+        let rows = build_query(&statement, var_bindings)
+            .fetch_all(&mut *executor)
+            .await?;
+
+        let nodes = rows.iter().map(&decode).collect::<Result<Vec<_>, _>>()?;
+
+        let mut connection = self.paginate(nodes)?;
+
+        // a locked page doesn't include the row after it, so the next cursor is looked up separately
+        if self.lock.is_some() && Some(connection.edges.len() as i64) == self.limit {
+            if let Some(cursor) = connection.edges.last().map(|edge| edge.cursor.clone()) {
+                connection.page_info.next_cursor =
+                    self.fetch_next_cursor(executor, &cursor, &decode).await?;
+            }
+        }
+
+        Ok(connection)
+    }
+
+    /// Finds the cursor of the row following the one at `cursor`, without locking either of them
+    async fn fetch_next_cursor<N>(
+        mut self,
+        executor: &mut PgConnection,
+        cursor: &Cursor,
+        decode: &impl Fn(&PgRow) -> Result<WithCursor<N>, Error>,
+    ) -> Result<Option<Cursor>, Error> {
+        self.lock = None;
+        self.lock_wait = LockWait::Wait;
+        self.cursor = cursor.clone().into();
+        self.include_prev_page = false;
+        self.limit = 1.into();
+
+        let filters = self.build_filters()?;
+        let (statement, var_bindings) = self.prepare(filters)?;
+
+        let rows = build_query(&statement, var_bindings)
             .fetch_all(executor)
             .await?;
 
-        self.paginate(nodes)
+        for row in rows.iter() {
+            let next_cursor = build_cursor::<T, N>(&decode(row)?, &self.order_by)?;
+
+            if &next_cursor != cursor {
+                return Ok(next_cursor.into());
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn fetch_offset_page<N>(
+        self,
+        executor: &mut PgConnection,
+        decode: impl Fn(&PgRow) -> Result<WithCursor<N>, Error>,
+    ) -> Result<EdgeConnection<N>, Error> {
+        let offset = self.resolve_offset()?.unwrap_or_default();
+        let limit = self.limit.unwrap_or(DEFAULT_LIMIT);
+
+        if self.cursor.is_some() {
+            return Err(Error::bad_request(
+                "a cursor can't be combined with offset pagination",
+            ));
+        }
+
+        let filters = self.build_filters()?;
+        let (statement, var_bindings) = self.prepare(filters)?;
+
+        let rows = build_query(&statement, var_bindings)
+            .fetch_all(&mut *executor)
+            .await?;
+
+        let nodes = rows.iter().map(decode).collect::<Result<Vec<_>, _>>()?;
+
+        // count every row matching the filters, ignoring the page bounds
+        let total_count = self.fetch_count(executor).await?;
+
+        let nodes = nodes.into_iter().take(limit as usize).collect();
+
+        Ok(EdgeConnection {
+            edges: self.build_edges(nodes)?,
+            page_info: PageInfo {
+                page: (offset / limit + 1).into(),
+                per_page: limit.into(),
+                total_pages: (total_count / limit + i64::from(total_count % limit != 0)).into(),
+                total_count: total_count.into(),
+                ..Default::default()
+            },
+        })
     }
 
     pub async fn fetch_one(self, executor: &mut PgConnection) -> Result<T, Error> {
@@ -219,19 +760,164 @@ where
 }
 
 impl<T: Model> Select<T> {
+    /// Counts the distinct rows matching the filters, ignoring ordering, cursors and page bounds
+    pub async fn count(self, executor: &mut PgConnection) -> Result<i64, Error> {
+        self.fetch_count(executor).await
+    }
+
+    /// Checks whether any row matches the filters without fetching it
+    pub async fn exists(self, executor: &mut PgConnection) -> Result<bool, Error> {
+        let filters = self.build_filters()?;
+        let (from_clause, var_bindings) = self.prepare_from_clause(filters)?;
+
+        let statement = format!("SELECT EXISTS(SELECT 1 {})", from_clause);
+
+        tracing::info!("{}", statement);
+
+        let (exists,) = build_query_as::<(bool,)>(&statement, var_bindings)
+            .fetch_one(executor)
+            .await?;
+
+        Ok(exists)
+    }
+
     pub(crate) fn new() -> Self {
         Self {
             select_path: T::table_name(),
+            columns: None,
+            includes: IncludeTree::default(),
+            limited_includes: vec![],
             filters: vec![],
             order_by: OrderBy::IdAsc,
             cursor: None,
             limit: None,
-            for_update: false,
-            _marker: PhantomData::default(),
+            offset: None,
+            page: None,
+            max_offset: DEFAULT_MAX_OFFSET,
+            include_prev_page: true,
+            lock: None,
+            lock_wait: LockWait::Wait,
+            set_operation: None,
+            traversal: None,
+            deleted: DeletedRows::Exclude,
+            _marker: PhantomData,
         }
     }
 
-    pub(crate) fn paginate(&self, nodes: Vec<WithCursor<T>>) -> Result<Connection<T>, Error> {
+    fn set_operation(mut self, op: SetOperator, other: Select<T>) -> Self {
+        let mut left = Select::new();
+        left.filters = std::mem::take(&mut self.filters);
+        left.set_operation = self.set_operation.take();
+        left.traversal = self.traversal.take();
+
+        // each operand excludes its own deleted rows, so the combined rows don't have to
+        left.deleted = std::mem::replace(&mut self.deleted, DeletedRows::Include);
+
+        self.set_operation = Some(Box::new((left, op, other)));
+        self
+    }
+
+    fn traverse(mut self, relation: &str, root: FieldValue, direction: TraversalDirection) -> Self {
+        self.traversal = Some(Traversal {
+            relation: relation.to_string(),
+            root,
+            direction,
+            max_depth: None,
+        });
+
+        self
+    }
+
+    /// Renders a single column select of the filtered rows, to be embedded in the filter of another model
+    pub(crate) fn to_subquery(&self) -> Result<Subquery, Error> {
+        let defs = self.selected_definitions()?;
+
+        let [def] = defs.as_slice() else {
+            return Err(Error::bad_request(
+                "invalid subquery: exactly one column has to be selected",
+            ));
+        };
+
+        let filters = self.build_filters()?;
+        let (from_clause, var_bindings) = self.prepare_from_clause(filters)?;
+
+        let statement = format!("SELECT {}.{} {}", T::table_name(), def.name, from_clause);
+
+        Ok(Subquery::Statement(statement, var_bindings))
+    }
+
+    /// The predicate excluding or only keeping soft deleted rows, None if they aren't filtered
+    pub(crate) fn soft_delete_predicate(&self) -> Option<String> {
+        let column = T::definition().soft_delete_column()?;
+
+        match self.deleted {
+            DeletedRows::Exclude => Some(format!("{}.{} IS NULL", T::table_name(), column)),
+            DeletedRows::Only => Some(format!("{}.{} IS NOT NULL", T::table_name(), column)),
+            DeletedRows::Include => None,
+        }
+    }
+
+    /// The table rows are selected from. A set operation or a traversal is wrapped in a subquery
+    /// aliased as the table, so the filters, joins and ordering can refer to it like the table itself
+    fn prepare_source(&self) -> Result<(String, Vec<FieldValue>), Error> {
+        let table_name = T::table_name();
+
+        if let Some(traversal) = &self.traversal {
+            if self.set_operation.is_some() {
+                return Err(Error::bad_request(
+                    "invalid select: call descendants_of or ancestors_of before union, intersect or except",
+                ));
+            }
+
+            return traversal.to_source_sql::<T>(self.deleted == DeletedRows::Exclude);
+        }
+
+        let Some(set_operation) = &self.set_operation else {
+            return Ok((table_name, vec![]));
+        };
+
+        let (left, op, right) = set_operation.as_ref();
+
+        let (left_from_clause, mut var_bindings) =
+            left.prepare_from_clause(left.build_filters()?)?;
+        let (right_from_clause, right_bindings) =
+            right.prepare_from_clause(right.build_filters()?)?;
+
+        let right_from_clause = shift_placeholders(&right_from_clause, var_bindings.len());
+        var_bindings.extend(right_bindings);
+
+        let source = format!(
+            "((SELECT {}.* {}) {} (SELECT {}.* {})) AS {}",
+            table_name, left_from_clause, op, table_name, right_from_clause, table_name
+        );
+
+        Ok((source, var_bindings))
+    }
+
+    async fn fetch_count(&self, executor: &mut PgConnection) -> Result<i64, Error> {
+        let filters = self.build_filters()?;
+        let (from_clause, var_bindings) = self.prepare_from_clause(filters)?;
+
+        let statement = format!(
+            "SELECT COUNT(DISTINCT {}.{}) {}",
+            T::table_name(),
+            T::id_field_name(),
+            from_clause
+        );
+
+        tracing::info!("{}", statement);
+
+        let (count,) = build_query_as::<(i64,)>(&statement, var_bindings)
+            .fetch_one(executor)
+            .await?;
+
+        Ok(count)
+    }
+
+    pub(crate) fn paginate<N>(
+        &self,
+        nodes: Vec<WithCursor<N>>,
+    ) -> Result<EdgeConnection<N>, Error> {
         let mut prev_cursor = None;
 
         let mut page_nodes = if let Some(cursor) = &self.cursor {
@@ -241,12 +927,11 @@ impl<T: Model> Select<T> {
             });
             tracing::info!("END RETURNED ROWS");
 
-            let (prev, next) = split_nodes(nodes, cursor, &self.order_by)?;
+            let (prev, next) = split_nodes::<T, N>(nodes, cursor, &self.order_by)?;
 
             prev_cursor = prev
-                .iter()
-                .next()
-                .map(|n| build_cursor(n, &self.order_by))
+                .first()
+                .map(|n| build_cursor::<T, N>(n, &self.order_by))
                 .transpose()?;
 
             next
@@ -260,52 +945,98 @@ impl<T: Model> Select<T> {
                     Error::internal("cursor_node should not be empty. this is a bug")
                 })?;
 
-                let next_cursor = build_cursor(&cursor_node, &self.order_by)?;
+                let next_cursor = build_cursor::<T, N>(&cursor_node, &self.order_by)?;
 
-                Ok(Connection {
-                    nodes: page_nodes.into_iter().map(|n| n.node).collect(),
+                Ok(EdgeConnection {
+                    edges: self.build_edges(page_nodes)?,
                     page_info: PageInfo {
                         prev_cursor,
                         next_cursor: next_cursor.into(),
+                        ..Default::default()
                     },
                 })
             }
-            _ => Ok(Connection {
-                nodes: page_nodes.into_iter().map(|n| n.node).collect(),
+            _ => Ok(EdgeConnection {
+                edges: self.build_edges(page_nodes)?,
                 page_info: PageInfo {
                     prev_cursor,
                     next_cursor: None,
+                    ..Default::default()
                 },
             }),
         }
     }
 
+    fn build_edges<N>(&self, nodes: Vec<WithCursor<N>>) -> Result<Vec<Edge<N>>, Error> {
+        nodes
+            .into_iter()
+            .map(|n| {
+                let cursor = build_cursor::<T, N>(&n, &self.order_by)?;
+
+                Ok(Edge {
+                    node: n.node,
+                    cursor,
+                })
+            })
+            .collect()
+    }
+
+    fn is_offset_paginated(&self) -> bool {
+        self.offset.is_some() || self.page.is_some()
+    }
+
+    /// The offset of offset pagination, which must not be negative nor exceed `max_offset`
+    fn resolve_offset(&self) -> Result<Option<i64>, Error> {
+        let offset = match (self.page, self.offset) {
+            (Some(page), _) => page_offset(page, self.limit.unwrap_or(DEFAULT_LIMIT))?,
+            (None, Some(offset)) => offset,
+            (None, None) => return Ok(None),
+        };
+
+        if offset < 0 {
+            return Err(Error::bad_request("offset must not be negative"));
+        }
+
+        if offset > self.max_offset {
+            return Err(Error::bad_request(&format!(
+                "offset must not exceed {}",
+                self.max_offset
+            )));
+        }
+
+        Ok(offset.into())
+    }
+
     /// prepares a query statement that fetches a max size of limit * 2 + 1.
     /// includes limit + 1 rows after the provided cursor and limit rows before
     pub(crate) fn prepare(&self, exprs: Vec<Expr>) -> Result<(String, Vec<FieldValue>), Error> {
         let table_name = T::table_name();
         let id_field_name = T::id_field_name();
 
+        let offset = self.resolve_offset()?;
+
+        let columns = self.select_columns()?;
+
+        let (source, source_bindings) = self.prepare_source()?;
+
         let select_clause = format!(
-            "SELECT {}.*, {}, TRUE AS _next_page FROM {}",
-            self.select_path,
+            "SELECT {}, {}, TRUE AS _next_page FROM {}",
+            columns,
             self.order_by.selects::<T>(),
-            table_name
+            source
         );
 
-        let order_by_references_relation = self.order_by.references_relation();
-
         let mut vars = vec![];
 
         match &self.order_by {
-            OrderBy::SecondaryAsc(var) | OrderBy::SecondaryDesc(var) => {
+            OrderBy::SecondaryAsc(var, _) | OrderBy::SecondaryDesc(var, _) => {
                 vars.push(var.clone());
             }
             _ => {}
         }
 
         let mut predicates = vec![];
-        let mut var_bindings = vec![];
+        let mut var_bindings = source_bindings;
 
         for expr in exprs.into_iter() {
             let (sql, v, b) = expr.to_sql::<T>(var_bindings.len());
@@ -317,17 +1048,49 @@ impl<T: Model> Select<T> {
             var_bindings.extend(b);
         }
 
+        predicates.extend(self.soft_delete_predicate());
+
+        self.validate_lock()?;
+
+        if self.lock.is_some() {
+            // postgres can't lock rows grouped to deduplicate the joins of a relation filter,
+            // so relations are only joined within a subquery selecting the matching ids
+            let join_clause = generate_join_clause::<T>(&vars)?;
+
+            if !join_clause.is_empty() {
+                let id_reference = format!("{}.{}", table_name, id_field_name);
+
+                predicates = vec![format!(
+                    "{} IN (SELECT {} FROM {} {} {})",
+                    id_reference,
+                    id_reference,
+                    table_name,
+                    join_clause,
+                    generate_where_clause(&predicates)
+                )];
+                vars.clear();
+            }
+        }
+
+        // one row beyond the page is fetched to find the next cursor, unless it would be locked too
+        let lookahead = if self.lock.is_some() { 0 } else { 1 };
+
         let mut statement = match &self.cursor {
             Some(cursor) => {
                 let inverse_select_clause = format!(
-                    "SELECT {}.*, {}, FALSE AS _next_page FROM {}",
-                    self.select_path,
+                    "SELECT {}, {}, FALSE AS _next_page FROM {}",
+                    columns,
                     self.order_by.selects::<T>(),
-                    table_name
+                    source
                 );
 
                 let mut inverse_predicates = predicates.clone();
 
+                // cursors over an aggregate have to be applied after grouping
+                let mut having = vec![];
+                let mut inverse_having = vec![];
+                let cursor_in_having = self.order_by.aggregate().is_some();
+
                 tracing::info!("building cursor filter");
 
                 let cursor_filter =
@@ -340,7 +1103,11 @@ impl<T: Model> Select<T> {
 
                 tracing::info!("cursor predicate: {}", sql);
 
-                predicates.push(sql);
+                if cursor_in_having {
+                    having.push(sql);
+                } else {
+                    predicates.push(sql);
+                }
                 vars.extend(v);
                 var_bindings.extend(b);
 
@@ -348,8 +1115,15 @@ impl<T: Model> Select<T> {
 
                 tracing::info!("inverse cursor predicate: {}", sql);
 
-                inverse_predicates.push(sql);
-                var_bindings.extend(b);
+                if cursor_in_having {
+                    inverse_having.push(sql);
+                } else {
+                    inverse_predicates.push(sql);
+                }
+
+                if self.include_prev_page {
+                    var_bindings.extend(b);
+                }
 
                 tracing::info!("building join clause");
 
@@ -357,14 +1131,13 @@ impl<T: Model> Select<T> {
 
                 tracing::info!("join clause: {}", join_clause);
 
-                let predicate = predicates.join(" AND ");
-                let where_clause = format!("WHERE {}", predicate);
+                let where_clause = generate_where_clause(&predicates);
+                let inverse_where_clause = generate_where_clause(&inverse_predicates);
 
-                let inverse_predicate = inverse_predicates.join(" AND ");
-                let inverse_where_clause = format!("WHERE {}", inverse_predicate);
+                let having_clause = generate_having_clause(&having);
+                let inverse_having_clause = generate_having_clause(&inverse_having);
 
-                let group_by_clause =
-                    generate_group_by_clause::<T>(join_clause != "", order_by_references_relation);
+                let group_by_clause = self.group_by_clause(!join_clause.is_empty());
 
                 tracing::info!("building order_by clause");
 
@@ -381,7 +1154,7 @@ impl<T: Model> Select<T> {
                 tracing::info!("inverse_order_by clause: {}", inverse_order_by_clause);
 
                 let limit_clause = match self.limit {
-                    Some(limit) if limit > 0 => format!("LIMIT {}", limit + 1),
+                    Some(limit) if limit > 0 => format!("LIMIT {}", limit + lookahead),
                     _ => "".into(),
                 };
 
@@ -393,11 +1166,13 @@ impl<T: Model> Select<T> {
                             {}
                             {}
                             {}
+                            {}
                     ",
                     select_clause,
                     join_clause,
                     where_clause,
                     group_by_clause,
+                    having_clause,
                     order_by_clause,
                     limit_clause
                 );
@@ -410,17 +1185,22 @@ impl<T: Model> Select<T> {
                             {}
                             {}
                             {}
+                            {}
                     ",
                     inverse_select_clause,
                     join_clause,
                     inverse_where_clause,
                     group_by_clause,
+                    inverse_having_clause,
                     inverse_order_by_clause,
                     limit_clause
                 );
 
-                format!(
-                    "
+                if !self.include_prev_page {
+                    next_page_query
+                } else {
+                    format!(
+                        "
                         WITH aggregated AS (
                             (
                                 {}
@@ -436,8 +1216,9 @@ impl<T: Model> Select<T> {
                         FROM aggregated
                         {}
                     ",
-                    previous_page_query, next_page_query, order_by_clause
-                )
+                        previous_page_query, next_page_query, order_by_clause
+                    )
+                }
             }
             _ => {
                 tracing::info!("building join clause");
@@ -446,15 +1227,9 @@ impl<T: Model> Select<T> {
 
                 tracing::info!("join clause: {}", join_clause);
 
-                let predicate = predicates.join(" AND ");
-                let where_clause = if predicate != "" {
-                    format!("WHERE {}", predicate)
-                } else {
-                    "".into()
-                };
+                let where_clause = generate_where_clause(&predicates);
 
-                let group_by_clause =
-                    generate_group_by_clause::<T>(join_clause != "", order_by_references_relation);
+                let group_by_clause = self.group_by_clause(!join_clause.is_empty());
 
                 tracing::info!("building order_by clause");
 
@@ -464,7 +1239,12 @@ impl<T: Model> Select<T> {
                 tracing::info!("order_by clause: {}", order_by_clause);
 
                 let limit_clause = match self.limit {
-                    Some(limit) if limit > 0 => format!("LIMIT {}", limit + 1),
+                    Some(limit) if limit > 0 => format!("LIMIT {}", limit + lookahead),
+                    _ => "".into(),
+                };
+
+                let offset_clause = match offset {
+                    Some(offset) if offset > 0 => format!("OFFSET {}", offset),
                     _ => "".into(),
                 };
 
@@ -476,24 +1256,27 @@ impl<T: Model> Select<T> {
                         {}
                         {}
                         {}
+                        {}
                     ",
                     select_clause,
                     join_clause,
                     where_clause,
                     group_by_clause,
                     order_by_clause,
-                    limit_clause
+                    limit_clause,
+                    offset_clause
                 )
             }
         };
 
-        if self.for_update {
+        if let Some(lock) = &self.lock {
             statement = format!(
                 "
                     {}
-                    FOR UPDATE
+                    {}
                 ",
-                statement
+                statement,
+                lock.to_sql(&table_name, &self.lock_wait)
             );
         }
 
@@ -503,11 +1286,144 @@ impl<T: Model> Select<T> {
         Ok((statement, var_bindings))
     }
 
+    /// prepares the FROM, JOIN and WHERE clauses of the filters. since nothing is selected
+    /// per row, joined relations don't have to be grouped
+    pub(crate) fn prepare_from_clause(
+        &self,
+        exprs: Vec<Expr>,
+    ) -> Result<(String, Vec<FieldValue>), Error> {
+        let (source, source_bindings) = self.prepare_source()?;
+
+        let mut vars = vec![];
+        let mut predicates = vec![];
+        let mut var_bindings = source_bindings;
+
+        for expr in exprs.into_iter() {
+            let (sql, v, b) = expr.to_sql::<T>(var_bindings.len());
+
+            predicates.push(sql);
+            vars.extend(v);
+            var_bindings.extend(b);
+        }
+
+        predicates.extend(self.soft_delete_predicate());
+
+        let join_clause = generate_join_clause::<T>(&vars)?;
+        let where_clause = generate_where_clause(&predicates);
+
+        let from_clause = format!(
+            "
+                FROM {}
+                {}
+                {}
+            ",
+            source, join_clause, where_clause
+        );
+
+        Ok((from_clause, var_bindings))
+    }
+
+    fn group_by_clause(&self, references_relation: bool) -> String {
+        // the primary key of a subquery doesn't determine its other columns, so they have to be grouped as well
+        let derived_source = self.set_operation.is_some() || self.traversal.is_some();
+
+        let mut group_by_clause = generate_group_by_clause::<T>(
+            references_relation,
+            self.order_by.references_relation() || derived_source,
+        );
+
+        if !group_by_clause.is_empty() && self.traversal.is_some() {
+            group_by_clause.push_str(&format!(", {}._depth", T::table_name()));
+        }
+
+        group_by_clause
+    }
+
+    fn validate_lock(&self) -> Result<(), Error> {
+        if self.lock.is_none() {
+            if self.lock_wait != LockWait::Wait {
+                return Err(Error::bad_request(
+                    "invalid lock: skip_locked and nowait require a row lock like for_update",
+                ));
+            }
+
+            return Ok(());
+        }
+
+        if self.order_by.references_relation() {
+            return Err(Error::bad_request(
+                "invalid lock: rows sorted by a relation can't be locked",
+            ));
+        }
+
+        if self.set_operation.is_some() {
+            return Err(Error::bad_request(
+                "invalid lock: the rows of a union, intersect or except can't be locked",
+            ));
+        }
+
+        if self.traversal.is_some() {
+            return Err(Error::bad_request(
+                "invalid lock: the rows of descendants_of or ancestors_of can't be locked",
+            ));
+        }
+
+        if self.cursor.is_some() && self.include_prev_page {
+            return Err(Error::bad_request(
+                "invalid lock: a page fetched with a cursor can't be locked, use batches instead",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// The definitions of the selected columns, every field if no columns were specified
+    fn selected_definitions(&self) -> Result<Vec<FieldDefinition>, Error> {
+        let defs = T::field_definitions();
+
+        let Some(columns) = &self.columns else {
+            return Ok(defs);
+        };
+
+        columns
+            .iter()
+            .map(|column| {
+                defs.iter()
+                    .find(|def| &def.name == column)
+                    .cloned()
+                    .ok_or_else(|| Error::bad_request(&format!("invalid column: {}", column)))
+            })
+            .collect()
+    }
+
+    fn select_columns(&self) -> Result<String, Error> {
+        if self.columns.is_none() {
+            return Ok(format!("{}.*", self.select_path));
+        }
+
+        let columns = self
+            .selected_definitions()?
+            .iter()
+            .map(|def| format!("{}.{}", self.select_path, def.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        Ok(columns)
+    }
+
     pub(crate) fn build_filters(&self) -> Result<Vec<Expr>, Error> {
         let mut results = vec![];
 
         for filter in self.filters.clone().into_iter() {
-            results.push(filter.build::<T>()?);
+            let expr = filter.build::<T>()?;
+
+            if expr.contains_aggregate() {
+                return Err(Error::bad_request(
+                    "invalid filter: aggregates can only be used in having",
+                ));
+            }
+
+            results.push(expr);
         }
 
         Ok(results)
@@ -520,14 +1436,28 @@ impl<T: Model> TryFrom<Query<T>> for Select<T> {
         let id_field_name = T::id_field_name();
 
         let order_by = match query.sort {
-            Some(sort) if sort.field.to_string() == id_field_name => match sort.direction {
-                SortDirection::Ascending => OrderBy::IdAsc,
-                SortDirection::Descending => OrderBy::IdDesc,
-            },
-            Some(sort) => match sort.direction {
-                SortDirection::Ascending => OrderBy::SecondaryAsc(sort.field.clone()),
-                SortDirection::Descending => OrderBy::SecondaryDesc(sort.field.clone()),
-            },
+            Some(sort) if sort.field.to_string() == id_field_name && sort.aggregate.is_none() => {
+                match sort.direction {
+                    SortDirection::Ascending => OrderBy::IdAsc,
+                    SortDirection::Descending => OrderBy::IdDesc,
+                }
+            }
+            Some(sort) => {
+                let aggregate =
+                    match (&sort.field, sort.aggregate) {
+                        (Var::Leaf(_), Some(_)) => return Err(Error::bad_request(
+                            "invalid sort: aggregates can only be applied to fields of a relation",
+                        )),
+                        // a relation has to be reduced to a single value per row, default to its maximum
+                        (Var::Node(_), None) => Some(Aggregate::Max),
+                        (_, aggregate) => aggregate,
+                    };
+
+                match sort.direction {
+                    SortDirection::Ascending => OrderBy::SecondaryAsc(sort.field, aggregate),
+                    SortDirection::Descending => OrderBy::SecondaryDesc(sort.field, aggregate),
+                }
+            }
             None => OrderBy::IdAsc,
         };
 
@@ -538,25 +1468,80 @@ impl<T: Model> TryFrom<Query<T>> for Select<T> {
 
         Ok(Select {
             select_path: T::table_name(),
+            columns: None,
+            includes: IncludeTree::default(),
+            limited_includes: vec![],
             filters,
             order_by,
             cursor: query.cursor,
             limit: query.limit,
-            for_update: false,
-            _marker: PhantomData::default(),
+            offset: query.offset,
+            page: query.page,
+            max_offset: DEFAULT_MAX_OFFSET,
+            include_prev_page: true,
+            lock: None,
+            lock_wait: LockWait::Wait,
+            set_operation: None,
+            traversal: None,
+            deleted: DeletedRows::Exclude,
+            _marker: PhantomData,
         })
     }
 }
 
+/// The number of rows skipped to reach the 1-based `page`, rejecting pages past the last
+/// representable offset
+fn page_offset(page: i64, per_page: i64) -> Result<i64, Error> {
+    if page < 1 {
+        return Err(Error::bad_request("page must be 1 or greater"));
+    }
+
+    if per_page < 1 {
+        return Err(Error::bad_request("per_page must be 1 or greater"));
+    }
+
+    (page - 1)
+        .checked_mul(per_page)
+        .ok_or_else(|| Error::bad_request("page is out of range"))
+}
+
 fn build_cursor_filter<T: Model>(
     cursor: &Cursor,
     id_field_name: &str,
     order_by: &OrderBy,
 ) -> Result<Expr, Error> {
     let filter = match order_by {
-        OrderBy::IdAsc => Filter::new().field(id_field_name).gte(cursor.id),
-        OrderBy::IdDesc => Filter::new().field(id_field_name).lte(cursor.id),
-        OrderBy::SecondaryAsc(secondary) => {
+        OrderBy::IdAsc => Filter::new().field(id_field_name).gte(cursor.id.clone()),
+        OrderBy::IdDesc => Filter::new().field(id_field_name).lte(cursor.id.clone()),
+        OrderBy::SecondaryAsc(secondary, Some(aggregate))
+        | OrderBy::SecondaryDesc(secondary, Some(aggregate)) => {
+            let secondary_value = cursor.value.clone().ok_or_else(|| Error::bad_request("invalid cursor: a cursor containing a value referencing the sort_by field is required"))?;
+
+            let (op, id_op) = match order_by {
+                OrderBy::SecondaryAsc(_, _) => (CompOp::Gt, CompOp::Gte),
+                _ => (CompOp::Lt, CompOp::Lte),
+            };
+
+            // aggregates can't be expressed through the filter language, so build the expression directly
+            let aggregate = Box::new(Expr::Agg(aggregate.clone(), secondary.clone()));
+            let value = Box::new(Expr::Val(secondary_value));
+
+            let id = Box::new(Expr::Var(Var::Leaf(id_field_name.into())));
+            let id_value = Box::new(Expr::Val(cursor.id.clone()));
+
+            let expr = Expr::Disj(
+                Box::new(Expr::Comp(aggregate.clone(), op, value.clone())),
+                LogicOp::Or,
+                Box::new(Expr::Conj(
+                    Box::new(Expr::Comp(aggregate, CompOp::Eq, value)),
+                    LogicOp::And,
+                    Box::new(Expr::Comp(id, id_op, id_value)),
+                )),
+            );
+
+            return Ok(expr);
+        }
+        OrderBy::SecondaryAsc(secondary, None) => {
             let secondary = secondary.to_string();
 
             let secondary_value = cursor.value.clone().ok_or_else(|| Error::bad_request("invalid cursor: a cursor containing a value referencing the sort_by field is required"))?;
@@ -570,10 +1555,10 @@ fn build_cursor_filter<T: Model>(
                         .eq(secondary_value)
                         .and()
                         .field(id_field_name)
-                        .gte(cursor.id),
+                        .gte(cursor.id.clone()),
                 )
         }
-        OrderBy::SecondaryDesc(secondary) => {
+        OrderBy::SecondaryDesc(secondary, None) => {
             let secondary = secondary.to_string();
 
             let secondary_value = cursor.value.clone().ok_or_else(|| Error::bad_request("invalid cursor: a cursor containing a value referencing the sort_by field is required"))?;
@@ -587,7 +1572,7 @@ fn build_cursor_filter<T: Model>(
                         .eq(secondary_value)
                         .and()
                         .field(id_field_name)
-                        .lte(cursor.id),
+                        .lte(cursor.id.clone()),
                 )
         }
     };
@@ -595,28 +1580,25 @@ fn build_cursor_filter<T: Model>(
     filter.build::<T>()
 }
 
-fn build_cursor<T: Model>(node: &WithCursor<T>, order_by: &OrderBy) -> Result<Cursor, Error> {
+fn build_cursor<T: Model, N>(node: &WithCursor<N>, order_by: &OrderBy) -> Result<Cursor, Error> {
+    // the id is selected separately so that projections without the id column can be paginated
+    let id = T::Id::field_type().parse_value(&node._cursor_id)?;
+
     let cursor = match order_by {
-        OrderBy::IdAsc | OrderBy::IdDesc => Cursor {
-            id: node.node.id_field_value(),
-            value: None,
-        }
-        .into(),
-        OrderBy::SecondaryAsc(var) | OrderBy::SecondaryDesc(var) => {
-            let model_def = T::definition();
-            let def = var.resolve_definition(&model_def)?;
+        OrderBy::IdAsc | OrderBy::IdDesc => Cursor { id, value: None },
+        OrderBy::SecondaryAsc(_, _) | OrderBy::SecondaryDesc(_, _) => {
+            let type_ = order_by.cursor_value_type::<T>()?.ok_or_else(|| {
+                Error::internal("secondary orderings must have a cursor value type. this is a bug")
+            })?;
 
             let value = node
                 ._cursor
                 .as_ref()
-                .map(|cursor| def.type_.parse_value(cursor))
+                .map(|cursor| type_.parse_value(cursor))
                 .transpose()?
-                .or_else(|| def.type_.null_value().into());
+                .or_else(|| type_.null_value().into());
 
-            Cursor {
-                id: node.node.id_field_value(),
-                value,
-            }
+            Cursor { id, value }
         }
         .into(),
     };
@@ -624,16 +1606,17 @@ fn build_cursor<T: Model>(node: &WithCursor<T>, order_by: &OrderBy) -> Result<Cu
     Ok(cursor)
 }
 
-fn split_nodes<T: Model>(
-    nodes: Vec<WithCursor<T>>,
+#[allow(clippy::type_complexity)]
+fn split_nodes<T: Model, N>(
+    nodes: Vec<WithCursor<N>>,
     cursor: &Cursor,
     order_by: &OrderBy,
-) -> Result<(Vec<WithCursor<T>>, Vec<WithCursor<T>>), Error> {
+) -> Result<(Vec<WithCursor<N>>, Vec<WithCursor<N>>), Error> {
     let mut prev = vec![];
     let mut next = vec![];
 
     for node in nodes.into_iter() {
-        let c = build_cursor(&node, order_by)?;
+        let c = build_cursor::<T, N>(&node, order_by)?;
 
         if node._next_page {
             next.push(node);
@@ -648,7 +1631,7 @@ fn split_nodes<T: Model>(
     Ok((prev, next))
 }
 
-fn generate_join_clause<T: Model>(vars: &Vec<Var>) -> Result<String, Error> {
+pub(super) fn generate_join_clause<T: Model>(vars: &[Var]) -> Result<String, Error> {
     let mut seen = HashSet::new();
     let mut join_clauses = vec![];
 
@@ -705,6 +1688,22 @@ fn joins_from_var(
     }
 }
 
+pub(super) fn generate_where_clause(predicates: &[String]) -> String {
+    if predicates.is_empty() {
+        return "".into();
+    }
+
+    format!("WHERE {}", predicates.join(" AND "))
+}
+
+pub(super) fn generate_having_clause(predicates: &[String]) -> String {
+    if predicates.is_empty() {
+        return "".into();
+    }
+
+    format!("HAVING {}", predicates.join(" AND "))
+}
+
 fn generate_group_by_clause<T: Model>(
     references_relation: bool,
     order_by_references_relation: bool,
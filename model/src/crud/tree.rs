@@ -0,0 +1,113 @@
+use crate::relation::Reference;
+use crate::{Error, FieldValue, Model};
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum TraversalDirection {
+    Descendants,
+    Ancestors,
+}
+
+/// Walks a self-referential `belongs_to` relation from a root row, see `Select::descendants_of`
+/// and `Select::ancestors_of`
+#[derive(Clone, Debug)]
+pub(crate) struct Traversal {
+    pub(crate) relation: String,
+    pub(crate) root: FieldValue,
+    pub(crate) direction: TraversalDirection,
+    pub(crate) max_depth: Option<i64>,
+}
+
+impl Traversal {
+    /// Renders a recursive query aliased as the table, selecting the columns of the model along with
+    /// a `_depth` column counting from 1 for the children or the parent of the root. Every path
+    /// remembers the rows it visited, so cycles end the recursion instead of looping forever.
+    /// Unless `exclude_deleted` is false, the walk stops at soft deleted rows
+    pub(crate) fn to_source_sql<T: Model>(
+        &self,
+        exclude_deleted: bool,
+    ) -> Result<(String, Vec<FieldValue>), Error> {
+        let table_name = T::table_name();
+        let id_field_name = T::id_field_name();
+
+        let relation_def = T::relation_definitions()
+            .into_iter()
+            .find(|def| def.name == self.relation)
+            .ok_or_else(|| Error::bad_request(&format!("invalid relation: {}", self.relation)))?;
+
+        let column = match &relation_def.reference {
+            Reference::From(column)
+                if (relation_def.model_definition.table_name)() == table_name =>
+            {
+                column
+            }
+            _ => {
+                return Err(Error::bad_request(&format!(
+                    "invalid relation: {} has to be a belongs_to relation of the model to itself",
+                    self.relation
+                )))
+            }
+        };
+
+        let (anchor_clause, step_condition) = match self.direction {
+            TraversalDirection::Descendants => (
+                format!("WHERE _node.{} = $1", column),
+                format!("_node.{} = _tree.{}", column, id_field_name),
+            ),
+            TraversalDirection::Ancestors => (
+                format!(
+                    "JOIN {} AS _child ON _child.{} = _node.{} WHERE _child.{} = $1",
+                    table_name, column, id_field_name, id_field_name
+                ),
+                format!("_node.{} = _tree.{}", id_field_name, column),
+            ),
+        };
+
+        let deleted_condition = match T::definition().soft_delete_column() {
+            Some(column) if exclude_deleted => format!("AND _node.{} IS NULL", column),
+            _ => "".into(),
+        };
+
+        let depth_condition = match self.max_depth {
+            Some(max_depth) => format!("AND _tree._depth < {}", max_depth),
+            None => "".into(),
+        };
+
+        let columns = T::field_definitions()
+            .into_iter()
+            .map(|def| def.name)
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let source = format!(
+            "
+                (
+                    WITH RECURSIVE _tree AS (
+                        SELECT _node.*, 1 AS _depth, ARRAY[$1, _node.{id}] AS _path
+                        FROM {table} AS _node
+                        {anchor_clause}
+                        {deleted_condition}
+
+                        UNION ALL
+
+                        SELECT _node.*, _tree._depth + 1, _tree._path || _node.{id}
+                        FROM {table} AS _node
+                        JOIN _tree ON {step_condition}
+                        WHERE NOT _node.{id} = ANY(_tree._path)
+                        {deleted_condition}
+                        {depth_condition}
+                    )
+                    SELECT {columns}, _depth FROM _tree
+                ) AS {table}
+            ",
+            id = id_field_name,
+            table = table_name,
+            anchor_clause = anchor_clause,
+            step_condition = step_condition,
+            deleted_condition = deleted_condition,
+            depth_condition = depth_condition,
+            columns = columns,
+        );
+
+        Ok((source, vec![self.root.clone()]))
+    }
+}
@@ -1,7 +1,10 @@
 use sqlx::{postgres::PgRow, FromRow, PgConnection};
 
 use crate::Error;
-use crate::{crud::util::build_query, Model};
+use crate::{
+    crud::util::{build_query, build_query_as, inline_bindings, version_increment},
+    FieldValue, Model,
+};
 
 #[derive(Clone, Debug)]
 pub struct Update<'a, T: Model> {
@@ -16,44 +19,130 @@ where
         Self { value }
     }
 
+    /// Writes every mutable field. With a version field the update fails with `Error::Conflict`
+    /// if the row was changed since the value was read. Soft deleted rows are left unchanged
     pub async fn execute(&self, executor: &mut PgConnection) -> Result<(), Error> {
+        let Some((statement, var_bindings)) = self.to_sql()? else {
+            return Ok(());
+        };
+
+        let result = build_query(&statement, var_bindings)
+            .execute(executor)
+            .await?;
+
+        if result.rows_affected() == 0 && has_version::<T>() {
+            return Err(version_conflict());
+        }
+
+        Ok(())
+    }
+
+    /// Like `execute`, but returns the updated row including its new version
+    pub async fn execute_returning(&self, executor: &mut PgConnection) -> Result<T, Error> {
+        let Some((statement, var_bindings)) = self.to_sql()? else {
+            return Err(Error::bad_request(
+                "invalid update: the model doesn't have any mutable fields",
+            ));
+        };
+
+        let statement = format!("{} RETURNING *", statement);
+
+        let updated = build_query_as(&statement, var_bindings)
+            .fetch_optional(executor)
+            .await?;
+
+        match updated {
+            Some(updated) => Ok(updated),
+            None if has_version::<T>() => Err(version_conflict()),
+            None => Err(sqlx::Error::RowNotFound.into()),
+        }
+    }
+
+    /// Renders the statement along with its bindings, None if there are no mutable fields to update
+    pub fn to_sql(&self) -> Result<Option<(String, Vec<FieldValue>)>, Error> {
         let table_name = T::table_name();
         let id_field_name = T::id_field_name();
         let fields = self.value.fields()?;
 
         let id_field_value = fields.iter().find(|(def, _)| def.name == id_field_name).map(|(_, val)| val.clone()).ok_or_else(|| Error::internal("id_field_name does not match any of the fields defined on the model. this should never happen!"))?;
 
+        let version = fields.iter().find(|(def, _)| def.version).cloned();
+
         let fields = fields.into_iter().filter_map(|(def, val)| {
-            if def.immutable {
+            if def.immutable || def.read_only || def.version || def.updated_at || def.soft_delete {
                 None
             } else {
                 (def, val).into()
             }
         });
 
-        let set_string = fields
+        let mut set_clauses = fields
             .clone()
             .enumerate()
             .map(|(i, (def, _))| format!("{} = ${}", def.name, i + 2))
-            .collect::<Vec<String>>()
-            .join(", ");
+            .collect::<Vec<String>>();
 
-        if set_string == "" {
-            return Ok(());
+        if set_clauses.is_empty() {
+            return Ok(None);
+        }
+
+        let mut var_bindings = vec![id_field_value];
+        var_bindings.extend(fields.into_iter().map(|(_, value)| value));
+
+        if let Some(def) = T::field_definitions()
+            .iter()
+            .find(|def| def.updated_at && !def.version)
+        {
+            set_clauses.push(format!("{} = now()", def.name));
+        }
+
+        let mut where_clause = format!("{} = $1", id_field_name);
+
+        // deleted rows are only changed by restoring them
+        if let Some(column) = T::definition().soft_delete_column() {
+            where_clause = format!("{} AND {} IS NULL", where_clause, column);
+        }
+
+        if let Some((def, value)) = version {
+            set_clauses.push(format!(
+                "{} = {}",
+                def.name,
+                version_increment(&def, &def.name)
+            ));
+
+            var_bindings.push(value);
+            where_clause = format!(
+                "{} AND {} = ${}",
+                where_clause,
+                def.name,
+                var_bindings.len()
+            );
         }
 
         let statement = format!(
-            "UPDATE {} SET {} WHERE {} = $1",
-            table_name, set_string, id_field_name
+            "UPDATE {} SET {} WHERE {}",
+            table_name,
+            set_clauses.join(", "),
+            where_clause
         );
 
-        let mut var_bindings = vec![id_field_value];
-        var_bindings.extend(fields.into_iter().map(|(_, value)| value));
+        Ok(Some((statement, var_bindings)))
+    }
 
-        build_query(&statement, var_bindings)
-            .execute(executor)
-            .await?;
+    /// Renders the statement with its bindings inlined, e.g. to paste it into psql
+    pub fn to_pretty_sql(&self) -> Result<Option<String>, Error> {
+        let statement = self
+            .to_sql()?
+            .map(|(statement, var_bindings)| inline_bindings(&statement, &var_bindings));
 
-        Ok(())
+        Ok(statement)
     }
 }
+
+pub(super) fn has_version<T: Model>() -> bool {
+    T::field_definitions().iter().any(|def| def.version)
+}
+
+pub(super) fn version_conflict() -> Error {
+    Error::conflict("the row was changed or deleted since it was read")
+}
@@ -0,0 +1,190 @@
+use sqlx::{postgres::PgRow, FromRow, PgConnection};
+
+use crate::Error;
+use crate::{
+    crud::{
+        util::{build_query, build_query_as, check_value, inline_bindings, version_increment},
+        Select,
+    },
+    FieldType, FieldValue, Filter, Model,
+};
+
+#[derive(Clone, Debug)]
+enum Assignment {
+    Value(FieldValue),
+    Expr(String),
+    Increment(FieldValue),
+}
+
+/// Updates every row matched by a filter in a single statement, see `Crud::update_where`
+#[derive(Clone, Debug)]
+pub struct UpdateWhere<T: Model> {
+    select: Select<T>,
+    assignments: Vec<(String, Assignment)>,
+}
+
+impl<T> UpdateWhere<T>
+where
+    T: Model + for<'b> FromRow<'b, PgRow> + Unpin + Sized + Send,
+{
+    pub(crate) fn new(filter: Filter) -> Self {
+        let mut select = Select::new();
+
+        // an empty filter matches every row
+        if !filter.is_empty() {
+            select = select.with_filter(filter);
+        }
+
+        Self {
+            select,
+            assignments: vec![],
+        }
+    }
+
+    /// Sets the field to the value, which has to match the type of the field
+    pub fn set(mut self, field: &str, value: impl Into<FieldValue>) -> Self {
+        self.assignments
+            .push((field.to_string(), Assignment::Value(value.into())));
+        self
+    }
+
+    /// Sets the field to a sql expression, e.g. `now()` or `price * 2`. The expression is
+    /// inserted into the statement as is, so it must never contain user input
+    pub fn set_expr(mut self, field: &str, expr: &str) -> Self {
+        self.assignments
+            .push((field.to_string(), Assignment::Expr(expr.to_string())));
+        self
+    }
+
+    /// Adds the value to a numeric field, atomically with respect to concurrent updates.
+    /// Null fields stay null
+    pub fn increment(mut self, field: &str, value: impl Into<FieldValue>) -> Self {
+        self.assignments
+            .push((field.to_string(), Assignment::Increment(value.into())));
+        self
+    }
+
+    /// Updates the matched rows and returns how many were changed
+    pub async fn execute(self, executor: &mut PgConnection) -> Result<u64, Error> {
+        let (statement, var_bindings) = self.to_sql()?;
+
+        tracing::info!("{}", statement);
+
+        let result = build_query(&statement, var_bindings)
+            .execute(executor)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Like `execute`, but returns the updated rows
+    pub async fn execute_returning(self, executor: &mut PgConnection) -> Result<Vec<T>, Error> {
+        let (statement, var_bindings) = self.to_sql()?;
+
+        let statement = format!("{} RETURNING *", statement);
+
+        tracing::info!("{}", statement);
+
+        let rows = build_query_as(&statement, var_bindings)
+            .fetch_all(executor)
+            .await?;
+
+        Ok(rows)
+    }
+
+    pub fn to_sql(&self) -> Result<(String, Vec<FieldValue>), Error> {
+        let table_name = T::table_name();
+        let id_field_name = T::id_field_name();
+        let defs = T::field_definitions();
+
+        if self.assignments.is_empty() {
+            return Err(Error::bad_request("invalid update: no field is set"));
+        }
+
+        // the matching rows are selected like a select would, so relation filters can be used
+        let filters = self.select.build_filters()?;
+        let (from_clause, mut var_bindings) = self.select.prepare_from_clause(filters)?;
+
+        let mut set_clauses = vec![];
+
+        for (name, assignment) in self.assignments.iter() {
+            let def = defs.iter().find(|def| &def.name == name).ok_or_else(|| {
+                Error::bad_request(&format!("invalid update: unknown field {}", name))
+            })?;
+
+            if def.immutable || def.read_only || def.version || def.updated_at || def.soft_delete {
+                return Err(Error::bad_request(&format!(
+                    "invalid update: {} can't be changed",
+                    name
+                )));
+            }
+
+            if set_clauses.iter().any(|(n, _)| n == name) {
+                return Err(Error::bad_request(&format!(
+                    "invalid update: {} is set more than once",
+                    name
+                )));
+            }
+
+            let clause = match assignment {
+                Assignment::Value(value) => {
+                    var_bindings.push(check_value(def, value.clone())?);
+                    format!("${}", var_bindings.len())
+                }
+                Assignment::Expr(expr) => expr.clone(),
+                Assignment::Increment(value) => {
+                    if !matches!(
+                        def.type_,
+                        FieldType::Int | FieldType::Int32 | FieldType::Float | FieldType::Decimal
+                    ) {
+                        return Err(Error::bad_request(&format!(
+                            "invalid update: {} is not a numeric field",
+                            name
+                        )));
+                    }
+
+                    var_bindings.push(check_value(def, value.clone())?);
+                    format!("{} + ${}", name, var_bindings.len())
+                }
+            };
+
+            set_clauses.push((name.clone(), clause));
+        }
+
+        let mut set_clauses = set_clauses
+            .into_iter()
+            .map(|(name, clause)| format!("{} = {}", name, clause))
+            .collect::<Vec<_>>();
+
+        if let Some(def) = defs.iter().find(|def| def.version) {
+            set_clauses.push(format!(
+                "{} = {}",
+                def.name,
+                version_increment(def, &def.name)
+            ));
+        }
+
+        if let Some(def) = defs.iter().find(|def| def.updated_at && !def.version) {
+            set_clauses.push(format!("{} = now()", def.name));
+        }
+
+        let statement = format!(
+            "UPDATE {} SET {} WHERE {}.{} IN (SELECT {}.{} {})",
+            table_name,
+            set_clauses.join(", "),
+            table_name,
+            id_field_name,
+            table_name,
+            id_field_name,
+            from_clause
+        );
+
+        Ok((statement, var_bindings))
+    }
+
+    /// Renders the statement with its bindings inlined, e.g. to paste it into psql
+    pub fn to_pretty_sql(&self) -> Result<String, Error> {
+        let (statement, var_bindings) = self.to_sql()?;
+        Ok(inline_bindings(&statement, &var_bindings))
+    }
+}
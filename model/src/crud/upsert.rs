@@ -1,13 +1,93 @@
-use sqlx::{postgres::PgRow, FromRow, PgConnection};
+use sqlx::{postgres::PgRow, FromRow, PgConnection, Row};
 
+use crate::index::IndexType;
 use crate::Error;
 use crate::{FieldValue, Model};
 
-use super::util::build_query_as;
+use super::update::version_conflict;
+use super::util::{build_query, inline_bindings, insert_values, version_increment};
+
+/// What an upsert did with the row
+#[derive(Clone, Debug, PartialEq)]
+pub enum UpsertOutcome {
+    Inserted,
+    Updated,
+    /// The row conflicted with an existing one and `do_nothing` was set
+    Skipped,
+}
+
+/// The unique columns an upsert checks for an existing row
+#[derive(Clone, Debug)]
+pub(crate) enum ConflictTarget {
+    PrimaryKey,
+    Fields(Vec<String>),
+    Constraint(String),
+    Index(String),
+}
+
+impl ConflictTarget {
+    /// Renders the target following `ON CONFLICT`
+    pub(crate) fn to_sql<T: Model>(&self) -> Result<String, Error> {
+        let defs = T::field_definitions();
+
+        let columns = match self {
+            Self::PrimaryKey => defs
+                .iter()
+                .filter(|def| def.primary_key)
+                .map(|def| def.name.clone())
+                .collect::<Vec<_>>(),
+            Self::Fields(fields) => {
+                if fields.is_empty() {
+                    return Err(Error::bad_request(
+                        "invalid conflict target: no field is given",
+                    ));
+                }
+
+                for field in fields.iter() {
+                    if !defs.iter().any(|def| &def.name == field) {
+                        return Err(Error::bad_request(&format!(
+                            "invalid conflict target: unknown field {}",
+                            field
+                        )));
+                    }
+                }
+
+                fields.clone()
+            }
+            // constraints aren't declared on the model, so the database validates the name
+            Self::Constraint(name) => return Ok(format!("ON CONSTRAINT {}", name)),
+            Self::Index(name) => {
+                let index = T::index_definitions()
+                    .into_iter()
+                    .find(|def| &def.name == name)
+                    .ok_or_else(|| {
+                        Error::bad_request(&format!(
+                            "invalid conflict target: unknown index {}",
+                            name
+                        ))
+                    })?;
+
+                if !index.unique || !matches!(index.type_, IndexType::BTree) {
+                    return Err(Error::bad_request(&format!(
+                        "invalid conflict target: index {} is not unique",
+                        name
+                    )));
+                }
+
+                index.columns
+            }
+        };
+
+        Ok(format!("({})", columns.join(", ")))
+    }
+}
 
 #[derive(Debug)]
 pub struct Upsert<'a, T> {
     value: &'a mut T,
+    conflict_target: ConflictTarget,
+    do_nothing: bool,
+    update_columns: Option<Vec<String>>,
 }
 
 impl<'a, T> Upsert<'a, T>
@@ -15,50 +95,185 @@ where
     T: Model + for<'b> FromRow<'b, PgRow> + Unpin + Sized + Send,
 {
     pub(crate) fn new(value: &'a mut T) -> Self {
-        Self { value }
+        Self {
+            value,
+            conflict_target: ConflictTarget::PrimaryKey,
+            do_nothing: false,
+            update_columns: None,
+        }
+    }
+
+    /// Checks for an existing row by the given fields instead of the primary key, e.g. a natural
+    /// key like `(organization_id, external_ref)`. The fields need a unique constraint or index
+    pub fn on_conflict(mut self, fields: &[&str]) -> Self {
+        self.conflict_target =
+            ConflictTarget::Fields(fields.iter().map(|f| f.to_string()).collect());
+        self
+    }
+
+    /// Checks for an existing row by a named unique or exclusion constraint
+    pub fn on_constraint(mut self, name: &str) -> Self {
+        self.conflict_target = ConflictTarget::Constraint(name.to_string());
+        self
+    }
+
+    /// Checks for an existing row by the columns of a unique index declared through `Indexed`
+    pub fn on_index(mut self, name: &str) -> Self {
+        self.conflict_target = ConflictTarget::Index(name.to_string());
+        self
+    }
+
+    /// Leaves an existing row unchanged instead of updating it
+    pub fn do_nothing(mut self) -> Self {
+        self.do_nothing = true;
+        self
+    }
+
+    /// Only updates the given columns of an existing row. The version and `updated_at`
+    /// fields are still maintained
+    pub fn update_only(mut self, columns: &[&str]) -> Self {
+        self.update_columns = Some(columns.iter().map(|c| c.to_string()).collect());
+        self
+    }
+
+    /// Inserts the row or updates the existing one, replacing the value with the stored row.
+    /// The update fails with `Error::Conflict` if the stored version differs or the row is soft deleted.
+    /// If the existing row is left unchanged by `do_nothing`, the value is kept as is
+    pub async fn execute(self, executor: &mut PgConnection) -> Result<UpsertOutcome, Error> {
+        let (statement, var_bindings) = self.to_sql()?;
+
+        let row = build_query(&statement, var_bindings)
+            .fetch_optional(executor)
+            .await?;
+
+        let Some(row) = row else {
+            if self.do_nothing {
+                return Ok(UpsertOutcome::Skipped);
+            }
+
+            return Err(version_conflict());
+        };
+
+        let inserted: bool = row.try_get("_inserted")?;
+
+        *self.value = T::from_row(&row)?;
+
+        if inserted {
+            Ok(UpsertOutcome::Inserted)
+        } else {
+            Ok(UpsertOutcome::Updated)
+        }
     }
 
-    pub async fn execute(self, executor: &mut PgConnection) -> Result<(), Error> {
+    pub fn to_sql(&self) -> Result<(String, Vec<FieldValue>), Error> {
         let table_name = T::table_name();
-        let fields = self.value.fields()?;
+        let (values, var_bindings) = insert_values(self.value.fields()?);
 
-        let columns = fields
+        let columns = values
             .iter()
             .map(|(def, _)| def.name.as_ref())
             .collect::<Vec<&str>>()
             .join(", ");
 
-        let tagged_fields = fields.iter().enumerate().map(|(i, field)| (i + 1, field));
-
-        let placeholder_values = tagged_fields
-            .clone()
-            .map(|(i, _)| format!("${}", i))
-            .collect::<Vec<String>>()
+        let placeholder_values = values
+            .iter()
+            .map(|(_, value)| value.as_ref())
+            .collect::<Vec<&str>>()
             .join(", ");
 
-        let primary_key_index = format!("{}_pkey", table_name);
+        let conflict_target = self.conflict_target.to_sql::<T>()?;
 
-        let placeholder_set = tagged_fields
-            .map(|(i, (def, _))| match def.immutable {
-                true => format!("{} = {}.{}", def.name, table_name, def.name),
-                false => format!("{} = ${}", def.name, i),
-            })
-            .collect::<Vec<String>>()
-            .join(", ");
+        let action = if self.do_nothing {
+            "DO NOTHING".to_string()
+        } else {
+            if let Some(update_columns) = &self.update_columns {
+                for column in update_columns.iter() {
+                    let def = values
+                        .iter()
+                        .map(|(def, _)| def)
+                        .find(|def| &def.name == column)
+                        .ok_or_else(|| {
+                            Error::bad_request(&format!(
+                                "invalid upsert: {} is not a writable field",
+                                column
+                            ))
+                        })?;
 
-        let statement = format!(
-            "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT ON CONSTRAINT {} DO UPDATE SET {} RETURNING *",
-            table_name, columns, placeholder_values, primary_key_index, placeholder_set
-        );
+                    if def.immutable || def.version || def.updated_at || def.soft_delete {
+                        return Err(Error::bad_request(&format!(
+                            "invalid upsert: {} can't be updated",
+                            column
+                        )));
+                    }
+                }
+            }
 
-        let var_bindings: Vec<FieldValue> = fields.into_iter().map(|(_, value)| value).collect();
+            // columns left to their default keep their current value on conflict, as does the
+            // deletion time so that upserts don't restore deleted rows
+            let placeholder_set = values
+                .iter()
+                .filter(|(def, _)| !def.soft_delete)
+                .filter(|(def, _)| {
+                    def.version
+                        || def.updated_at
+                        || self
+                            .update_columns
+                            .as_ref()
+                            .is_none_or(|columns| columns.contains(&def.name))
+                })
+                .map(|(def, value)| {
+                    let reference = format!("{}.{}", table_name, def.name);
 
-        let upserted: T = build_query_as(&statement, var_bindings)
-            .fetch_one(executor)
-            .await?;
+                    if def.version {
+                        format!("{} = {}", def.name, version_increment(def, &reference))
+                    } else if def.immutable || value == "DEFAULT" {
+                        format!("{} = {}", def.name, reference)
+                    } else {
+                        format!("{} = {}", def.name, value)
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(", ");
+
+            if placeholder_set.is_empty() {
+                return Err(Error::bad_request(
+                    "invalid upsert: there are no columns to update, use do_nothing instead",
+                ));
+            }
+
+            // the existing row is only updated if the version matches and it isn't deleted,
+            // otherwise nothing is returned
+            let mut conditions = values
+                .iter()
+                .filter(|(def, _)| def.version)
+                .map(|(def, value)| format!("{}.{} = {}", table_name, def.name, value))
+                .collect::<Vec<String>>();
+
+            if let Some(column) = T::definition().soft_delete_column() {
+                conditions.push(format!("{}.{} IS NULL", table_name, column));
+            }
 
-        *self.value = upserted;
+            let where_clause = if conditions.is_empty() {
+                "".to_string()
+            } else {
+                format!(" WHERE {}", conditions.join(" AND "))
+            };
+
+            format!("DO UPDATE SET {}{}", placeholder_set, where_clause)
+        };
+
+        // xmax is only set on rows that existed before, which tells an update from an insert
+        let statement = format!(
+            "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT {} {} RETURNING *, xmax = 0 AS _inserted",
+            table_name, columns, placeholder_values, conflict_target, action
+        );
+
+        Ok((statement, var_bindings))
+    }
 
-        Ok(())
+    /// Renders the statement with its bindings inlined, e.g. to paste it into psql
+    pub fn to_pretty_sql(&self) -> Result<String, Error> {
+        let (statement, var_bindings) = self.to_sql()?;
+        Ok(inline_bindings(&statement, &var_bindings))
     }
 }
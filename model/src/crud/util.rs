@@ -2,10 +2,10 @@ use sqlx::{
     postgres::{PgArguments, PgRow},
     query::{Query as SqlxQuery, QueryAs},
     types::Json,
-    FromRow, Postgres,
+    FromRow, Postgres, Row,
 };
 
-use crate::FieldValue;
+use crate::{Error, FieldDefinition, FieldType, FieldValue};
 
 pub fn build_query<'b>(
     statement: &'b str,
@@ -72,3 +72,172 @@ pub fn bind_query_as<'a, T>(
         FieldValue::Json(inner) => q.bind(Json(inner)),
     }
 }
+
+pub fn decode_column(row: &PgRow, name: &str, type_: &FieldType) -> Result<FieldValue, Error> {
+    let value = match type_ {
+        FieldType::Uuid => FieldValue::Uuid(row.try_get(name)?),
+        FieldType::Bool => FieldValue::Bool(row.try_get(name)?),
+        FieldType::Int => FieldValue::Int(row.try_get(name)?),
+        FieldType::Int32 => FieldValue::Int32(row.try_get(name)?),
+        FieldType::Float => FieldValue::Float(row.try_get(name)?),
+        FieldType::Decimal => FieldValue::Decimal(row.try_get(name)?),
+        FieldType::String => FieldValue::String(row.try_get(name)?),
+        FieldType::Date => FieldValue::Date(row.try_get(name)?),
+        FieldType::DateTime => FieldValue::DateTime(row.try_get(name)?),
+        FieldType::Enum(_) => FieldValue::Enum(row.try_get(name)?),
+        FieldType::Json => FieldValue::Json(row.try_get(name)?),
+    };
+
+    Ok(value)
+}
+
+/// Renders a statement for humans, with the bindings inlined as literals and the indentation
+/// of the generated statement removed
+pub fn inline_bindings(statement: &str, var_bindings: &[FieldValue]) -> String {
+    let statement = statement
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut output = String::with_capacity(statement.len());
+    let mut chars = statement.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            output.push(c);
+            continue;
+        }
+
+        let mut digits = String::new();
+
+        while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+            digits.push(*d);
+            chars.next();
+        }
+
+        let value = digits
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| var_bindings.get(i.wrapping_sub(1)));
+
+        match value {
+            Some(value) => output.push_str(&value.to_sql_literal()),
+            None => {
+                output.push(c);
+                output.push_str(&digits);
+            }
+        }
+    }
+
+    output
+}
+
+/// The expression bumping the version column of an update, see `#[model(version)]`
+pub fn version_increment(def: &FieldDefinition, reference: &str) -> String {
+    match def.type_ {
+        // now() is fixed for the transaction, so it wouldn't change between two updates within one
+        FieldType::DateTime => "clock_timestamp()".into(),
+        _ => format!("{} + 1", reference),
+    }
+}
+
+/// The set clauses marking a row as changed by a statement that doesn't write the model itself,
+/// like a soft delete: the version is incremented and the updated_at field set
+pub fn touch_clauses(defs: &[FieldDefinition]) -> Vec<String> {
+    let version = defs
+        .iter()
+        .filter(|def| def.version)
+        .map(|def| format!("{} = {}", def.name, version_increment(def, &def.name)));
+
+    let updated_at = defs
+        .iter()
+        .filter(|def| def.updated_at && !def.version)
+        .map(|def| format!("{} = now()", def.name));
+
+    version.chain(updated_at).collect()
+}
+
+/// Checks that the value can be written to the field, converting strings to enum variants,
+/// nulls to the type of the field and integers to wider numeric types, e.g. an unsuffixed literal
+pub fn check_value(def: &FieldDefinition, value: FieldValue) -> Result<FieldValue, Error> {
+    let null_value = def.type_.null_value();
+
+    if value.is_null() {
+        if !def.nullable {
+            return Err(Error::bad_request(&format!(
+                "invalid value: {} can't be null",
+                def.name
+            )));
+        }
+
+        return Ok(null_value);
+    }
+
+    match (&def.type_, value) {
+        (FieldType::Enum(variants), FieldValue::String(Some(variant)))
+        | (FieldType::Enum(variants), FieldValue::Enum(Some(variant))) => {
+            if !variants.contains(&variant) {
+                return Err(Error::bad_request(&format!(
+                    "invalid value: {} is not a variant of {}",
+                    variant, def.name
+                )));
+            }
+
+            Ok(FieldValue::Enum(variant.into()))
+        }
+        (FieldType::Int, FieldValue::Int32(Some(value))) => Ok(FieldValue::Int(Some(value.into()))),
+        (FieldType::Float, FieldValue::Int32(Some(value))) => {
+            Ok(FieldValue::Float(Some(value.into())))
+        }
+        // larger integers don't have an exact float
+        (FieldType::Float, FieldValue::Int(Some(value))) if (value as f64) as i64 == value => {
+            Ok(FieldValue::Float(Some(value as f64)))
+        }
+        (FieldType::Decimal, FieldValue::Int32(Some(value))) => {
+            Ok(FieldValue::Decimal(Some(value.into())))
+        }
+        (FieldType::Decimal, FieldValue::Int(Some(value))) => {
+            Ok(FieldValue::Decimal(Some(value.into())))
+        }
+        (_, value) if std::mem::discriminant(&value) == std::mem::discriminant(&null_value) => {
+            Ok(value)
+        }
+        _ => Err(Error::bad_request(&format!(
+            "invalid value: the value doesn't match the type of {}",
+            def.name
+        ))),
+    }
+}
+
+/// The columns written by an insert, each with its placeholder, `DEFAULT` or `now()`, along with the bindings.
+/// Read only columns are left to the database, as are null values of columns with a default.
+/// Timestamps are set by the database, unless they are compared as the version
+pub fn insert_values(
+    fields: Vec<(FieldDefinition, FieldValue)>,
+) -> (Vec<(FieldDefinition, String)>, Vec<FieldValue>) {
+    let mut columns = vec![];
+    let mut var_bindings = vec![];
+
+    for (def, value) in fields.into_iter() {
+        if def.read_only {
+            continue;
+        }
+
+        if (def.created_at || def.updated_at) && !def.version {
+            columns.push((def, "now()".to_string()));
+            continue;
+        }
+
+        if def.default.is_some() && value == def.type_.null_value() {
+            columns.push((def, "DEFAULT".to_string()));
+            continue;
+        }
+
+        var_bindings.push(value);
+        columns.push((def, format!("${}", var_bindings.len())));
+    }
+
+    (columns, var_bindings)
+}
@@ -8,12 +8,11 @@ use schemars::{
 };
 use serde::{Serialize, Serializer};
 use std::{borrow::Cow, cmp::Ordering};
-use uuid::Uuid;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Cursor {
     pub value: Option<FieldValue>,
-    pub id: Uuid,
+    pub id: FieldValue,
 }
 
 impl PartialOrd for Cursor {
@@ -30,26 +29,9 @@ impl Serialize for Cursor {
     where
         S: Serializer,
     {
-        let mut data = to_b64_str(&self.id).map_err(serde::ser::Error::custom)?;
+        let mut data = encode_value(&self.id).map_err(serde::ser::Error::custom)?;
         if let Some(value) = &self.value {
-            let value = match value {
-                FieldValue::Uuid(v) => to_b64_str(v),
-                FieldValue::Bool(v) => to_b64_str(v),
-                FieldValue::Int(v) => to_b64_str(v),
-                FieldValue::Int32(v) => to_b64_str(v),
-                FieldValue::Float(v) => to_b64_str(v),
-                FieldValue::Decimal(v) => to_b64_str(v),
-                FieldValue::String(v) => to_b64_str(v),
-                FieldValue::Date(v) => to_b64_str(v),
-                FieldValue::DateTime(v) => to_b64_str(v),
-                FieldValue::Enum(v) => to_b64_str(v),
-                FieldValue::Json(_) => {
-                    return Err(serde::ser::Error::custom(Error::bad_request(
-                        "can't serialize cursor that has a json value",
-                    )))
-                }
-            }
-            .map_err(serde::ser::Error::custom)?;
+            let value = encode_value(value).map_err(serde::ser::Error::custom)?;
 
             data = format!("{}_{}", &data, &value);
         }
@@ -58,6 +40,24 @@ impl Serialize for Cursor {
     }
 }
 
+fn encode_value(value: &FieldValue) -> Result<String, Error> {
+    match value {
+        FieldValue::Uuid(v) => to_b64_str(v),
+        FieldValue::Bool(v) => to_b64_str(v),
+        FieldValue::Int(v) => to_b64_str(v),
+        FieldValue::Int32(v) => to_b64_str(v),
+        FieldValue::Float(v) => to_b64_str(v),
+        FieldValue::Decimal(v) => to_b64_str(v),
+        FieldValue::String(v) => to_b64_str(v),
+        FieldValue::Date(v) => to_b64_str(v),
+        FieldValue::DateTime(v) => to_b64_str(v),
+        FieldValue::Enum(v) => to_b64_str(v),
+        FieldValue::Json(_) => Err(Error::bad_request(
+            "can't serialize cursor that has a json value",
+        )),
+    }
+}
+
 impl JsonSchema for Cursor {
     fn schema_name() -> String {
         // Exclude the module path to make the name in generated schemas clearer.
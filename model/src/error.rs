@@ -5,6 +5,7 @@ use std::fmt::Display;
 pub enum Error {
     NotFound(ErrorDetails),
     BadRequest(ErrorDetails),
+    Conflict(ErrorDetails),
     Unauthorized(ErrorDetails),
     InternalError(ErrorDetails),
 }
@@ -30,6 +31,14 @@ impl Error {
         })
     }
 
+    /// The row was changed since it was read, see `#[model(version)]`
+    pub fn conflict(message: &str) -> Self {
+        Self::Conflict(ErrorDetails {
+            code: "conflict".into(),
+            message: message.into(),
+        })
+    }
+
     pub fn unauthorized(message: &str) -> Self {
         Self::Unauthorized(ErrorDetails {
             code: "unauthorized".into(),
@@ -49,6 +58,7 @@ impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let msg = match self {
             Self::BadRequest(d)
+            | Self::Conflict(d)
             | Self::InternalError(d)
             | Self::NotFound(d)
             | Self::Unauthorized(d) => d.message.as_str(),
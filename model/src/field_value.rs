@@ -276,6 +276,23 @@ where
 }
 
 impl FieldValue {
+    pub fn is_null(&self) -> bool {
+        matches!(
+            self,
+            Self::Uuid(None)
+                | Self::Bool(None)
+                | Self::Int(None)
+                | Self::Int32(None)
+                | Self::Float(None)
+                | Self::Decimal(None)
+                | Self::String(None)
+                | Self::Date(None)
+                | Self::DateTime(None)
+                | Self::Json(None)
+                | Self::Enum(None)
+        )
+    }
+
     pub fn to_csv_string(&self) -> String {
         match self {
             Self::Uuid(Some(inner)) => inner.to_string(),
@@ -292,6 +309,27 @@ impl FieldValue {
             _ => "null".to_string(),
         }
     }
+
+    /// Renders the value as a postgres literal, only meant for displaying statements to humans
+    pub fn to_sql_literal(&self) -> String {
+        match self {
+            Self::Uuid(Some(inner)) => format!("'{}'::uuid", inner),
+            Self::Bool(Some(inner)) => inner.to_string().to_uppercase(),
+            Self::Int(Some(inner)) => inner.to_string(),
+            Self::Int32(Some(inner)) => inner.to_string(),
+            Self::Float(Some(inner)) => inner.to_string(),
+            Self::Decimal(Some(inner)) => inner.to_string(),
+            Self::String(Some(inner)) | Self::Enum(Some(inner)) => quote_sql_string(inner),
+            Self::Date(Some(inner)) => format!("'{}'::date", inner),
+            Self::DateTime(Some(inner)) => format!("'{}'::timestamptz", inner.to_rfc3339()),
+            Self::Json(Some(inner)) => format!("{}::jsonb", quote_sql_string(&inner.to_string())),
+            _ => "NULL".to_string(),
+        }
+    }
+}
+
+fn quote_sql_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
 }
 
 fn escape_csv_string(field: &str) -> String {
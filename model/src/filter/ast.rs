@@ -1,7 +1,11 @@
-use crate::{sort_by, Error, FieldDefinition, ModelDef};
+use std::fmt::Display;
+
+use crate::relation::Reference;
+use crate::{Error, FieldDefinition, FieldType, ModelDef};
 use crate::{FieldDefinitionMap, FieldValue, Filter, Model};
 
 use super::parser::ExprParser;
+use super::util::shift_placeholders;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Expr {
@@ -11,6 +15,16 @@ pub enum Expr {
     Neg(LogicOp, Box<Expr>),
     Conj(Box<Expr>, LogicOp, Box<Expr>),
     Disj(Box<Expr>, LogicOp, Box<Expr>),
+    Agg(Aggregate, Var),
+    In(Var, Subquery),
+}
+
+/// A single column select of another model, see `Filter::in_select`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Subquery {
+    /// Refers to the subqueries of a `Filter` by index until the filter is built
+    Placeholder(usize),
+    Statement(String, Vec<FieldValue>),
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -19,6 +33,16 @@ pub enum Var {
     Node((String, Box<Var>)),
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum Aggregate {
+    Count,
+    CountDistinct,
+    Min,
+    Max,
+    Sum,
+    Avg,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum LogicOp {
     Not,
@@ -40,6 +64,14 @@ impl Var {
         }
     }
 
+    /// Builds a var from a dot separated path like `cakes.price`
+    pub fn from_path(path: &str) -> Self {
+        match path.split_once('.') {
+            Some((name, rest)) => Self::Node((name.into(), Box::new(Self::from_path(rest)))),
+            None => Self::Leaf(path.into()),
+        }
+    }
+
     pub fn resolve_definition(&self, model_def: &ModelDef) -> Result<FieldDefinition, Error> {
         match self {
             Var::Leaf(name) => {
@@ -78,14 +110,59 @@ impl Var {
         }
     }
 
-    pub fn from_sort_by_str<T: Model>(input: &str) -> Result<Self, Error> {
-        let model_def = T::definition();
+    /// Resolves a path ending in a relation (e.g. `cakes` or `bakery.cakes`) to the id field of the related model
+    pub fn resolve_relation_id(&self, model_def: &ModelDef) -> Result<Var, Error> {
+        let (name, next) = match self {
+            Var::Leaf(name) => (name, None),
+            Var::Node((name, var)) => (name, Some(var)),
+        };
 
-        let var = sort_by::parser::ExprParser::new()
-            .parse(&model_def, input)
-            .map_err(|err| Error::bad_request(&format!("invalid sort_by: {:?}", err)))?;
+        let relation_defs = (model_def.relation_definitions)();
+        let relation_def = relation_defs
+            .iter()
+            .find(|def| &def.name == name)
+            .ok_or_else(|| {
+                let msg = format!("undefined relation: {}", name);
 
-        Ok(var)
+                tracing::error!("{}", msg);
+
+                Error::bad_request(msg.as_str())
+            })?;
+
+        let model_def = &relation_def.model_definition;
+
+        let var = match next {
+            Some(var) => var.resolve_relation_id(model_def)?,
+            None => Var::Leaf((model_def.id_field_name)()),
+        };
+
+        Ok(Var::Node((name.clone(), Box::new(var))))
+    }
+
+    /// Returns the relations of the path up to its last has_one, has_many or many-to-many
+    /// relation, which are the joins that may repeat the rows of the model
+    pub(crate) fn to_many_path(&self, model_def: &ModelDef) -> Result<Vec<String>, Error> {
+        let (name, var) = match self {
+            Var::Leaf(_) => return Ok(vec![]),
+            Var::Node((name, var)) => (name, var),
+        };
+
+        let relation_defs = (model_def.relation_definitions)();
+        let relation_def = relation_defs
+            .iter()
+            .find(|def| &def.name == name)
+            .ok_or_else(|| Error::bad_request(&format!("undefined relation: {}", name)))?;
+
+        let rest = var.to_many_path(&relation_def.model_definition)?;
+
+        if rest.is_empty() && matches!(relation_def.reference, Reference::From(_)) {
+            return Ok(vec![]);
+        }
+
+        let mut path = vec![name.clone()];
+        path.extend(rest);
+
+        Ok(path)
     }
 }
 
@@ -106,6 +183,79 @@ impl From<&str> for Var {
     }
 }
 
+impl Aggregate {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let aggregate = match name {
+            "count" => Self::Count,
+            "count_distinct" => Self::CountDistinct,
+            "min" => Self::Min,
+            "max" => Self::Max,
+            "sum" => Self::Sum,
+            "avg" => Self::Avg,
+            _ => return None,
+        };
+
+        Some(aggregate)
+    }
+
+    pub fn to_sql(&self, reference: &str) -> String {
+        match self {
+            Self::Count => format!("COUNT({})", reference),
+            Self::CountDistinct => format!("COUNT(DISTINCT {})", reference),
+            Self::Min => format!("MIN({})", reference),
+            Self::Max => format!("MAX({})", reference),
+            Self::Sum => format!("SUM({})", reference),
+            Self::Avg => format!("AVG({})", reference),
+        }
+    }
+
+    /// The type postgres returns when applying the aggregate to a field of the given type
+    pub fn result_type(&self, field_type: &FieldType) -> Result<FieldType, Error> {
+        let result_type = match (self, field_type) {
+            (_, FieldType::Json) => {
+                return Err(Error::bad_request(
+                    "invalid aggregate: json fields can't be aggregated",
+                ))
+            }
+            (Self::Count | Self::CountDistinct, _) => FieldType::Int,
+            (Self::Min | Self::Max, FieldType::Bool | FieldType::Uuid) => {
+                return Err(Error::bad_request(
+                    "invalid aggregate: min and max are not supported for bool and uuid fields",
+                ))
+            }
+            (Self::Min | Self::Max, type_) => type_.clone(),
+            (Self::Sum, FieldType::Int32) => FieldType::Int,
+            (Self::Sum, FieldType::Int | FieldType::Decimal) => FieldType::Decimal,
+            (Self::Sum | Self::Avg, FieldType::Float) => FieldType::Float,
+            (Self::Avg, FieldType::Int | FieldType::Int32 | FieldType::Decimal) => {
+                FieldType::Decimal
+            }
+            (Self::Sum | Self::Avg, _) => {
+                return Err(Error::bad_request(
+                    "invalid aggregate: sum and avg can only be applied to numeric fields",
+                ))
+            }
+        };
+
+        Ok(result_type)
+    }
+}
+
+impl Display for Aggregate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Count => "count",
+            Self::CountDistinct => "count_distinct",
+            Self::Min => "min",
+            Self::Max => "max",
+            Self::Sum => "sum",
+            Self::Avg => "avg",
+        };
+
+        f.write_str(name)
+    }
+}
+
 impl LogicOp {
     fn to_sql(&self) -> String {
         match self {
@@ -174,15 +324,103 @@ impl ToString for CompOp {
 
 impl Expr {
     pub fn from_str<T: Model>(input: &str) -> Result<Self, Error> {
+        let expr = Self::from_having_str::<T>(input)?;
+
+        if expr.contains_aggregate() {
+            return Err(Error::bad_request(
+                "invalid filter: aggregates can only be used in having",
+            ));
+        }
+
+        Ok(expr)
+    }
+
+    /// Parses a filter that may also compare aggregates, e.g. `count(cakes) > "2"`
+    pub fn from_having_str<T: Model>(input: &str) -> Result<Self, Error> {
         let model_def = T::definition();
 
         let boxed = ExprParser::new()
             .parse(&model_def, input)
             .map_err(|err| Error::bad_request(&format!("invalid filter: {:?}", err)))?;
 
+        if boxed.contains_subquery() {
+            return Err(Error::bad_request(
+                "invalid filter: subqueries can only be built with Filter::in_select",
+            ));
+        }
+
         Ok(*boxed)
     }
 
+    pub fn contains_subquery(&self) -> bool {
+        match self {
+            Expr::In(_, _) => true,
+            Expr::Var(_) | Expr::Val(_) | Expr::Agg(_, _) => false,
+            Expr::Neg(_, expr) => expr.contains_subquery(),
+            Expr::Comp(a, _, b) | Expr::Conj(a, _, b) | Expr::Disj(a, _, b) => {
+                a.contains_subquery() || b.contains_subquery()
+            }
+        }
+    }
+
+    /// Replaces the subquery placeholders of a parsed `Filter` with the statements they refer to
+    pub fn resolve_subqueries(&mut self, subqueries: &[Subquery]) -> Result<(), Error> {
+        match self {
+            Expr::In(_, subquery) => {
+                if let Subquery::Placeholder(idx) = subquery {
+                    *subquery = subqueries
+                        .get(*idx)
+                        .cloned()
+                        .ok_or_else(|| Error::internal("undefined subquery"))?;
+                }
+
+                Ok(())
+            }
+            Expr::Var(_) | Expr::Val(_) | Expr::Agg(_, _) => Ok(()),
+            Expr::Neg(_, expr) => expr.resolve_subqueries(subqueries),
+            Expr::Comp(a, _, b) | Expr::Conj(a, _, b) | Expr::Disj(a, _, b) => {
+                a.resolve_subqueries(subqueries)?;
+                b.resolve_subqueries(subqueries)
+            }
+        }
+    }
+
+    pub fn contains_aggregate(&self) -> bool {
+        match self {
+            Expr::Agg(_, _) => true,
+            Expr::Var(_) | Expr::Val(_) | Expr::In(_, _) => false,
+            Expr::Neg(_, expr) => expr.contains_aggregate(),
+            Expr::Comp(a, _, b) | Expr::Conj(a, _, b) | Expr::Disj(a, _, b) => {
+                a.contains_aggregate() || b.contains_aggregate()
+            }
+        }
+    }
+
+    /// Splits a conjunction into its operands, any other expression is returned as is
+    pub fn into_conjuncts(self) -> Vec<Expr> {
+        match self {
+            Expr::Conj(a, _, b) => {
+                let mut conjuncts = a.into_conjuncts();
+                conjuncts.extend(b.into_conjuncts());
+                conjuncts
+            }
+            expr => vec![expr],
+        }
+    }
+
+    /// Whether every var of the expression is the given field of the model itself
+    pub fn only_references(&self, field: &str) -> bool {
+        match self {
+            Expr::Var(Var::Leaf(name)) | Expr::In(Var::Leaf(name), _) => name == field,
+            Expr::Var(_) | Expr::Agg(_, _) | Expr::In(_, _) => false,
+            Expr::Val(_) => true,
+            Expr::Neg(_, expr) => expr.only_references(field),
+            Expr::Comp(a, _, b) | Expr::Conj(a, _, b) | Expr::Disj(a, _, b) => {
+                a.only_references(field) && b.only_references(field)
+            }
+        }
+    }
+
     pub fn to_sql<T: Model>(
         &self,
         var_binding_idx_offset: usize,
@@ -197,10 +435,37 @@ impl Expr {
 
                 (sql, vec![var.clone()], vec![])
             }
+            Expr::Agg(aggregate, var) => {
+                let mut reference = var.to_sql();
+
+                if matches!(var, Var::Leaf(_)) {
+                    reference = format!("{}.{}", T::table_name(), reference);
+                }
+
+                (aggregate.to_sql(&reference), vec![var.clone()], vec![])
+            }
             Expr::Val(val) => {
                 let sql = format!("${}", var_binding_idx_offset + 1);
                 (sql, vec![], vec![val.clone()])
             }
+            Expr::In(var, subquery) => {
+                let mut reference = var.to_sql();
+
+                if matches!(var, Var::Leaf(_)) {
+                    reference = format!("{}.{}", T::table_name(), reference);
+                }
+
+                match subquery {
+                    Subquery::Statement(statement, bindings) => {
+                        let statement = shift_placeholders(statement, var_binding_idx_offset);
+                        let sql = format!("{} IN ({})", reference, statement);
+
+                        (sql, vec![var.clone()], bindings.clone())
+                    }
+                    // unresolved placeholders are rejected when parsing, they never match anything
+                    Subquery::Placeholder(_) => ("FALSE".into(), vec![var.clone()], vec![]),
+                }
+            }
             Expr::Comp(a_expr, op, b_expr) => {
                 let (a_sql, mut a_vars, mut a_bindings) =
                     a_expr.to_sql::<T>(var_binding_idx_offset);
@@ -275,6 +540,9 @@ impl TryInto<Filter> for Expr {
                     _ => return Err(Error::internal("invalid filter expression: this should not happen as any errors should have been caught during parsing of the expression"))
                 }
             },
+            Expr::In(var, subquery) => {
+                Filter::new().field(&var.to_string()).in_subquery(subquery)
+            },
             Expr::Neg(_, exp) => {
                 Filter::new().not().group((*exp).try_into()?)
             },
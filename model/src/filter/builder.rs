@@ -1,3 +1,4 @@
+use crate::crud::Select;
 use crate::Error;
 use crate::{
     filter::{ast::*, parser::ExprParser},
@@ -11,6 +12,8 @@ enum Token {
     Val(FieldValue),
     Group(Filter),
     LogicOp(LogicOp),
+    /// Holds the error message if the select couldn't be rendered, returned when building
+    InSelect(Result<Subquery, String>),
 }
 
 #[derive(Clone, Debug)]
@@ -85,6 +88,22 @@ impl Filter {
         self
     }
 
+    /// Matches rows whose field is in the single column selected by another select,
+    /// e.g. `Filter::new().field("id").in_select(Cake::select().column("bakery_id"))`
+    pub fn in_select<U: Model>(mut self, select: Select<U>) -> Self {
+        let subquery = select.to_subquery().map_err(|err| err.to_string());
+        self.tokens.push(Token::InSelect(subquery));
+
+        self
+    }
+
+    pub(crate) fn in_subquery(mut self, subquery: Subquery) -> Self {
+        self.tokens.push(Token::InSelect(Ok(subquery)));
+
+        self
+    }
+
+    #[allow(clippy::should_implement_trait)]
     pub fn not(mut self) -> Self {
         self.tokens.push(Token::LogicOp(LogicOp::Not));
 
@@ -109,32 +128,50 @@ impl Filter {
         self
     }
 
+    pub(crate) fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
     pub fn build<T: Model>(self) -> Result<Expr, Error> {
         let model_def = T::definition();
 
-        let input = tokens_to_string(self.tokens);
+        let mut subqueries = vec![];
+        let input = tokens_to_string(self.tokens, &mut subqueries)?;
 
-        let expr = ExprParser::new()
+        let mut expr = ExprParser::new()
             .parse(&model_def, &input)
             .map_err(|err| Error::internal(&format!("{:?}", err)))?;
 
+        expr.resolve_subqueries(&subqueries)?;
+
         Ok(*expr)
     }
 }
 
-fn tokens_to_string(tokens: Vec<Token>) -> String {
+fn tokens_to_string(tokens: Vec<Token>, subqueries: &mut Vec<Subquery>) -> Result<String, Error> {
     let tokens = tokens
         .into_iter()
-        .map(|tok| match tok {
-            Token::Group(b) => format!("({})", tokens_to_string(b.tokens)),
-            Token::Var(var) => var,
-            Token::Val(val) => format!(r#""{}""#, val.to_string()),
-            Token::CompOp(op) => op.to_string(),
-            Token::LogicOp(op) => op.to_string(),
+        .map(|tok| {
+            let tok = match tok {
+                Token::Group(b) => format!("({})", tokens_to_string(b.tokens, subqueries)?),
+                Token::Var(var) => var,
+                Token::Val(val) => format!(r#""{}""#, val.to_string()),
+                Token::CompOp(op) => op.to_string(),
+                Token::LogicOp(op) => op.to_string(),
+                Token::InSelect(subquery) => {
+                    let subquery = subquery
+                        .map_err(|err| Error::bad_request(&format!("invalid subquery: {}", err)))?;
+
+                    subqueries.push(subquery);
+                    format!("IN @{}", subqueries.len() - 1)
+                }
+            };
+
+            Ok(tok)
         })
-        .collect::<Vec<String>>();
+        .collect::<Result<Vec<String>, Error>>()?;
 
-    tokens.join(" ")
+    Ok(tokens.join(" "))
 }
 
 #[cfg(test)]
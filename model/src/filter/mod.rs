@@ -8,8 +8,9 @@ lalrpop_mod!(pub(crate) parser, "/filter/grammar.rs");
 #[cfg(test)]
 mod test {
     use super::{
-        ast::{CompOp, Expr, LogicOp},
+        ast::{CompOp, Expr, LogicOp, Subquery, Var},
         parser::ExprParser,
+        util,
     };
     use crate as model;
     use crate::{relation::RelationDef, FieldDefinition, FieldType, FieldValue, ModelDef};
@@ -43,6 +44,13 @@ mod test {
                 primary_key: false,
                 unique: false,
                 nullable: false,
+                read_only: false,
+                default: None,
+                generated: None,
+                version: false,
+                created_at: false,
+                updated_at: false,
+                soft_delete: false,
             },
             FieldDefinition {
                 name: "start_date".into(),
@@ -51,6 +59,13 @@ mod test {
                 primary_key: false,
                 unique: false,
                 nullable: false,
+                read_only: false,
+                default: None,
+                generated: None,
+                version: false,
+                created_at: false,
+                updated_at: false,
+                soft_delete: false,
             },
             FieldDefinition {
                 name: "property_id".into(),
@@ -59,6 +74,13 @@ mod test {
                 primary_key: false,
                 unique: false,
                 nullable: false,
+                read_only: false,
+                default: None,
+                generated: None,
+                version: false,
+                created_at: false,
+                updated_at: false,
+                soft_delete: false,
             },
             FieldDefinition {
                 name: "max_occupancy".into(),
@@ -67,6 +89,13 @@ mod test {
                 primary_key: false,
                 unique: false,
                 nullable: false,
+                read_only: false,
+                default: None,
+                generated: None,
+                version: false,
+                created_at: false,
+                updated_at: false,
+                soft_delete: false,
             },
             FieldDefinition {
                 name: "name".into(),
@@ -75,6 +104,13 @@ mod test {
                 primary_key: false,
                 unique: false,
                 nullable: false,
+                read_only: false,
+                default: None,
+                generated: None,
+                version: false,
+                created_at: false,
+                updated_at: false,
+                soft_delete: false,
             },
             FieldDefinition {
                 name: "closed".into(),
@@ -83,6 +119,13 @@ mod test {
                 primary_key: false,
                 unique: false,
                 nullable: false,
+                read_only: false,
+                default: None,
+                generated: None,
+                version: false,
+                created_at: false,
+                updated_at: false,
+                soft_delete: false,
             },
             FieldDefinition {
                 name: "parent_id".into(),
@@ -91,6 +134,13 @@ mod test {
                 primary_key: false,
                 unique: false,
                 nullable: false,
+                read_only: false,
+                default: None,
+                generated: None,
+                version: false,
+                created_at: false,
+                updated_at: false,
+                soft_delete: false,
             },
         ]
     }
@@ -273,6 +323,28 @@ mod test {
         ExprParser::new().parse(&model_def, query).unwrap();
     }
 
+    #[test]
+    fn test_subquery_placeholders() {
+        let model_def = definition();
+        let query = r#"org_id = "1" && property_id IN @0"#;
+
+        let mut expr = ExprParser::new().parse(&model_def, query).unwrap();
+
+        let subquery = Subquery::Statement("SELECT id FROM property WHERE id > $1".into(), vec![]);
+        expr.resolve_subqueries(std::slice::from_ref(&subquery))
+            .unwrap();
+
+        assert!(expr.contains_subquery());
+        assert!(
+            matches!(*expr, Expr::Conj(_, _, ref b) if **b == Expr::In(Var::Leaf("property_id".into()), subquery))
+        );
+
+        assert_eq!(
+            util::shift_placeholders("id > $1 AND id < $12", 2),
+            "id > $3 AND id < $14"
+        );
+    }
+
     #[test]
     fn test_sql_generation() {
         let model_def = definition();
@@ -1,3 +1,12 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDate};
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::filter::ast::CompOp;
+use crate::{FieldType, FieldValue};
+
 pub fn apply_string_escapes(input: &str) -> String {
     let mut output = String::with_capacity(input.len());
     let mut chars = input.chars().peekable();
@@ -23,3 +32,78 @@ pub fn apply_string_escapes(input: &str) -> String {
 
     output
 }
+
+/// Parses the literal of a comparison into a value of the type being compared against
+pub fn parse_value(
+    type_: &FieldType,
+    op: &CompOp,
+    val: Option<String>,
+) -> Result<FieldValue, &'static str> {
+    let Some(val) = val else {
+        return Ok(type_.null_value());
+    };
+
+    let val = match type_ {
+        FieldType::Uuid => Uuid::parse_str(&val).map_err(|_| "invalid uuid")?.into(),
+        FieldType::Bool => bool::from_str(&val).map_err(|_| "invalid bool")?.into(),
+        FieldType::Int => i64::from_str(&val).map_err(|_| "invalid int")?.into(),
+        FieldType::Int32 => i32::from_str(&val).map_err(|_| "invalid int32")?.into(),
+        FieldType::Float => f64::from_str(&val).map_err(|_| "invalid float")?.into(),
+        FieldType::Decimal => Decimal::from_str(&val)
+            .map_err(|_| "invalid decimal")?
+            .into(),
+        FieldType::String => val.into(),
+        FieldType::Date => NaiveDate::parse_from_str(&val, "%Y-%m-%d")
+            .map_err(|_| "invalid date")?
+            .into(),
+        FieldType::DateTime => DateTime::parse_from_rfc3339(&val)
+            .map_err(|_| "invalid datetime")?
+            .into(),
+        FieldType::Enum(variants) => {
+            if let CompOp::Eq = op {
+                variants
+                    .iter()
+                    .find(|&v| v == &val)
+                    .ok_or("invalid enum variant")?;
+            }
+
+            FieldValue::Enum(val.to_string().into())
+        }
+        FieldType::Json => return Err("illegal field: can't filter by json field"),
+    };
+
+    Ok(val)
+}
+
+/// Renumbers the `$n` placeholders of a statement rendered on its own, so that it can be
+/// embedded in a statement with `offset` bindings before it
+pub fn shift_placeholders(statement: &str, offset: usize) -> String {
+    if offset == 0 {
+        return statement.to_string();
+    }
+
+    let mut output = String::with_capacity(statement.len());
+    let mut chars = statement.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        output.push(c);
+
+        if c != '$' {
+            continue;
+        }
+
+        let mut digits = String::new();
+
+        while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+            digits.push(*d);
+            chars.next();
+        }
+
+        match digits.parse::<usize>() {
+            Ok(idx) => output.push_str(&(idx + offset).to_string()),
+            Err(_) => output.push_str(&digits),
+        }
+    }
+
+    output
+}
@@ -0,0 +1,47 @@
+use std::fmt::Debug;
+
+use uuid::Uuid;
+
+use crate::{FieldType, FieldValue};
+
+/// Types that can be declared as the id field of a model
+pub trait ModelId: Clone + Debug + PartialEq + Into<FieldValue> + Send + Sync {
+    fn field_type() -> FieldType;
+}
+
+impl ModelId for Uuid {
+    fn field_type() -> FieldType {
+        FieldType::Uuid
+    }
+}
+
+impl ModelId for i64 {
+    fn field_type() -> FieldType {
+        FieldType::Int
+    }
+}
+
+impl ModelId for i32 {
+    fn field_type() -> FieldType {
+        FieldType::Int32
+    }
+}
+
+impl ModelId for String {
+    fn field_type() -> FieldType {
+        FieldType::String
+    }
+}
+
+/// Generates a time-ordered UUIDv7. Using these as ids makes the default id ordering of
+/// `Select` follow creation time instead of being random
+pub fn new_uuid() -> Uuid {
+    Uuid::now_v7()
+}
+
+/// Generates a UUIDv7 in the database, used as the column default of uuid ids. Postgres only
+/// ships uuidv7() from version 18 on, so the millisecond timestamp is written over the first
+/// 48 bits of a random uuid and the version bits are set to 7
+pub(crate) const UUID_V7_SQL: &str = "encode(set_bit(set_bit(overlay(uuid_send(gen_random_uuid()) \
+    placing substring(int8send(floor(extract(epoch from clock_timestamp()) * 1000)::bigint) from 3) \
+    from 1 for 6), 52, 1), 53, 1), 'hex')::uuid";
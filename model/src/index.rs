@@ -14,6 +14,7 @@ pub struct IndexDef {
     pub name: String,
     pub columns: Vec<String>,
     pub type_: IndexType,
+    pub unique: bool,
 }
 
 impl IndexDef {
@@ -22,6 +23,7 @@ impl IndexDef {
             name: name.to_string(),
             columns: vec![],
             type_: IndexType::BTree,
+            unique: false,
         }
     }
 
@@ -30,6 +32,12 @@ impl IndexDef {
         self
     }
 
+    /// Makes the index unique, so that it can also be used as the conflict target of an upsert
+    pub fn unique(mut self) -> Self {
+        self.unique = true;
+        self
+    }
+
     pub fn fulltext(mut self) -> Self {
         self.type_ = IndexType::Fulltext;
         self
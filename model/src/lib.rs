@@ -4,8 +4,11 @@ mod cursor;
 mod error;
 mod field_value;
 pub(crate) mod filter;
+mod id;
 mod index;
+mod loaded;
 mod model;
+mod patch;
 mod pgoutput;
 mod query;
 mod relation;
@@ -13,19 +16,25 @@ mod schema;
 pub(crate) mod sort_by;
 mod util;
 
-pub use connection::{Connection, PageInfo};
-pub use crud::Crud;
+pub use connection::{Connection, Edge, EdgeConnection, PageInfo};
+pub use crud::{Crud, Facet, UpsertCounts, UpsertOutcome};
 pub use cursor::Cursor;
 pub use enum_derive::Enum;
 pub use error::Error;
 pub use field_value::FieldValue;
+pub use filter::ast::Aggregate;
 pub use filter::builder::Filter;
+pub use id::{new_uuid, ModelId};
 pub use index::{IndexDef, Indexed};
+pub use loaded::Loaded;
 pub use model::*;
 pub use model_derive::Model;
 pub use model_wrapper::model_wrapper;
+pub use patch::{deserialize_some, ModelPatch};
 pub use pgoutput::FromPgoutput;
 pub use query::*;
 pub use relation::{Related, RelationDef};
 pub use schema::*;
+pub use serde;
+pub use serde_json;
 pub use sqlx;
@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::Error;
+
+/// A node together with its eagerly loaded relations, see `Select::include`.
+/// Related rows are kept as json nested under the relation name, many relations as arrays
+/// and belongs_to relations as a single object or null
+#[derive(Clone, Debug, Serialize)]
+pub struct Loaded<T> {
+    #[serde(flatten)]
+    pub node: T,
+    #[serde(flatten)]
+    pub relations: HashMap<String, Value>,
+}
+
+impl<T> Loaded<T> {
+    /// Decodes the rows loaded for the relation. Nested includes are available to R as fields named after the relation
+    pub fn related<R: DeserializeOwned>(&self, name: &str) -> Result<Vec<R>, Error> {
+        let value = self.relation(name)?;
+
+        let values = match value {
+            Value::Null => vec![],
+            Value::Array(values) => values.clone(),
+            value => vec![value.clone()],
+        };
+
+        values.into_iter().map(decode_related).collect()
+    }
+
+    /// Decodes the row loaded for a relation that references at most one row
+    pub fn related_one<R: DeserializeOwned>(&self, name: &str) -> Result<Option<R>, Error> {
+        Ok(self.related(name)?.into_iter().next())
+    }
+
+    fn relation(&self, name: &str) -> Result<&Value, Error> {
+        self.relations
+            .get(name)
+            .ok_or_else(|| Error::bad_request(&format!("relation {} was not included", name)))
+    }
+}
+
+fn decode_related<R: DeserializeOwned>(value: Value) -> Result<R, Error> {
+    serde_json::from_value(value)
+        .map_err(|err| Error::internal(&format!("couldn't deserialize related row: {:?}", err)))
+}
@@ -6,6 +6,7 @@ use std::collections::HashMap;
 use std::str::FromStr;
 use uuid::Uuid;
 
+use crate::id::ModelId;
 use crate::index::Indexed;
 use crate::relation::RelationDef;
 use crate::Error;
@@ -13,6 +14,8 @@ use crate::FieldValue;
 use crate::Related;
 
 pub trait Model: Related + Indexed {
+    type Id: ModelId;
+
     fn table_name() -> String;
     fn id_field_name() -> String;
     fn field_definitions() -> Vec<FieldDefinition>;
@@ -57,7 +60,7 @@ pub trait Model: Related + Indexed {
         RelationDef::has_many_via::<Self, R>(name.into(), junction_table_name.into())
     }
 
-    fn id_field_value(&self) -> Uuid;
+    fn id_field_value(&self) -> Self::Id;
     fn field_value(&self, field: &str) -> Result<FieldValue, Error>;
     fn fields(&self) -> Result<Vec<(FieldDefinition, FieldValue)>, Error> {
         let defs = Self::field_definitions();
@@ -80,6 +83,33 @@ pub struct ModelDef {
     pub relation_definitions: fn() -> Vec<RelationDef>,
 }
 
+impl ModelDef {
+    /// The column marking deleted rows, if the model is soft deleted
+    pub(crate) fn soft_delete_column(&self) -> Option<String> {
+        (self.field_definitions)()
+            .into_iter()
+            .find(|def| def.soft_delete)
+            .map(|def| def.name)
+    }
+
+    /// The SQL type of the id column, e.g. for columns referencing the model
+    pub(crate) fn id_sql_type(&self) -> Result<&'static str, Error> {
+        let id_field_name = (self.id_field_name)();
+
+        (self.field_definitions)()
+            .into_iter()
+            .find(|def| def.name == id_field_name)
+            .map(|def| def.type_.sql_type())
+            .ok_or_else(|| {
+                Error::internal(&format!(
+                    "id field {} of {} is not defined",
+                    id_field_name,
+                    (self.table_name)()
+                ))
+            })
+    }
+}
+
 pub trait Enum: Sized {
     fn try_from_string(value: String) -> Result<Self, Error>;
     fn to_string(self) -> String;
@@ -95,6 +125,20 @@ pub struct FieldDefinition {
     pub primary_key: bool,
     pub unique: bool,
     pub nullable: bool,
+    /// Never written by inserts or updates, e.g. identity columns or columns maintained by triggers
+    pub read_only: bool,
+    /// A default expression like `now()`, used by inserts when the value is null
+    pub default: Option<String>,
+    /// The expression of a `GENERATED ALWAYS AS` column, which is always read only
+    pub generated: Option<String>,
+    /// An integer or timestamp column bumped by every update, which fails if the row changed in between
+    pub version: bool,
+    /// Set to `now()` by inserts and kept by updates
+    pub created_at: bool,
+    /// Set to `now()` by inserts and updates
+    pub updated_at: bool,
+    /// Set to `now()` by `delete`, rows where it isn't null are excluded from selects and updates
+    pub soft_delete: bool,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -0,0 +1,23 @@
+use serde::{Deserialize, Deserializer};
+
+use crate::{Error, FieldValue, Model};
+
+/// A partial update of a model, derived as `{Model}Patch` with `#[model(patch)]`. Every field
+/// is optional, with nullable fields wrapped twice so that null can be set explicitly
+pub trait ModelPatch {
+    type Model: Model;
+
+    /// The names and values of the fields set on the patch
+    fn fields(&self) -> Result<Vec<(String, FieldValue)>, Error>;
+}
+
+/// Deserializes a present value, including null, into `Some`. Combined with `#[serde(default)]`
+/// this tells an omitted field apart from one explicitly set to null
+#[doc(hidden)]
+pub fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
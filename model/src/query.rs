@@ -3,16 +3,15 @@ use std::marker::PhantomData;
 
 use crate::cursor::Cursor;
 use crate::field_value::FieldValue;
-use crate::filter::ast::{Expr, Var};
+use crate::filter::ast::{Aggregate, Expr, Var};
 use crate::model::FieldType;
 use crate::util::from_b64_str;
-use crate::Model;
+use crate::{sort_by, Model, ModelId};
 use schemars::gen::SchemaGenerator;
 use schemars::schema::Schema;
 use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer, Serialize};
 pub use serde_json;
-use uuid::Uuid;
 
 use crate::Error;
 
@@ -23,6 +22,10 @@ struct RawQuery {
     pub sort_direction: Option<String>,
     pub cursor: Option<String>,
     pub limit: Option<i64>,
+    /// Switches to offset pagination, `per_page` is an alias for `limit`
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+    pub offset: Option<i64>,
 }
 
 #[derive(Clone, Debug)]
@@ -31,6 +34,9 @@ pub struct Query<T: Model> {
     pub sort: Option<Sort>,
     pub cursor: Option<Cursor>,
     pub limit: Option<i64>,
+    /// 1-based page number for offset pagination, takes precedence over offset
+    pub page: Option<i64>,
+    pub offset: Option<i64>,
 
     // internal fields
     _marker: PhantomData<T>,
@@ -39,6 +45,8 @@ pub struct Query<T: Model> {
 #[derive(Clone, Debug)]
 pub struct Sort {
     pub field: Var,
+    /// Sorts by an aggregate over a relation path, e.g. `count(cakes)` or `avg(cakes.price)`
+    pub aggregate: Option<Aggregate>,
     pub direction: SortDirection,
 }
 
@@ -64,6 +72,8 @@ impl<T: Model> Query<T> {
             sort: None,
             cursor: None,
             limit: None,
+            page: None,
+            offset: None,
             _marker: PhantomData,
         }
     }
@@ -126,7 +136,7 @@ impl<T: Model> TryFrom<RawQuery> for Query<T> {
         }
 
         if let Some(sort_by) = value.sort_by {
-            let field = Var::from_sort_by_str::<T>(&sort_by)?;
+            let (field, aggregate) = parse_sort_by::<T>(&sort_by)?;
             let field_def = field.resolve_definition(&model_def)?;
 
             if let FieldType::Json = field_def.type_ {
@@ -135,8 +145,14 @@ impl<T: Model> TryFrom<RawQuery> for Query<T> {
                 ));
             }
 
+            let value_type = match &aggregate {
+                Some(aggregate) => aggregate.result_type(&field_def.type_)?,
+                None => field_def.type_,
+            };
+
             let mut sort = Sort {
                 field,
+                aggregate,
                 // this is the default sort direction
                 direction: SortDirection::Ascending,
             };
@@ -155,18 +171,55 @@ impl<T: Model> TryFrom<RawQuery> for Query<T> {
                 let cursor_value = if sort_by == T::id_field_name() {
                     None
                 } else {
-                    (&field_def.type_).into()
+                    (&value_type).into()
                 };
 
-                let cursor = parse_cursor(&cursor, cursor_value)?;
+                let cursor = parse_cursor::<T>(&cursor, cursor_value)?;
                 query.cursor = cursor.into();
             }
         } else if let Some(cursor) = value.cursor {
-            let cursor = parse_cursor(&cursor, None)?;
+            let cursor = parse_cursor::<T>(&cursor, None)?;
             query.cursor = cursor.into();
         }
 
-        query.limit = value.limit;
+        query.limit = match (value.limit, value.per_page) {
+            (Some(_), Some(_)) => {
+                return Err(Error::bad_request(
+                    "only one of limit or per_page may be provided",
+                ))
+            }
+            (limit, per_page) => limit.or(per_page),
+        };
+
+        if query.cursor.is_some() && (value.page.is_some() || value.offset.is_some()) {
+            return Err(Error::bad_request(
+                "a cursor can't be combined with page or offset",
+            ));
+        }
+
+        match (value.page, value.offset) {
+            (Some(_), Some(_)) => {
+                return Err(Error::bad_request(
+                    "only one of page or offset may be provided",
+                ))
+            }
+            (Some(page), _) if page < 1 => {
+                return Err(Error::bad_request("page must be 1 or greater"));
+            }
+            (_, Some(offset)) if offset < 0 => {
+                return Err(Error::bad_request("offset must not be negative"));
+            }
+            (page, offset) => {
+                query.page = page;
+                query.offset = offset;
+            }
+        }
+
+        if (query.page.is_some() || query.offset.is_some())
+            && query.limit.is_some_and(|limit| limit < 1)
+        {
+            return Err(Error::bad_request("per_page must be 1 or greater"));
+        }
 
         Ok(query)
     }
@@ -176,6 +229,14 @@ fn parse_filter<T: Model>(filter: &str) -> Result<Expr, Error> {
     Expr::from_str::<T>(filter)
 }
 
+fn parse_sort_by<T: Model>(sort_by: &str) -> Result<(Var, Option<Aggregate>), Error> {
+    let model_def = T::definition();
+
+    sort_by::parser::ExprParser::new()
+        .parse(&model_def, sort_by)
+        .map_err(|err| Error::bad_request(&format!("invalid sort_by: {:?}", err)))
+}
+
 fn parse_sort_direction(sort_direction: &str) -> Result<SortDirection, Error> {
     match sort_direction {
         "1" => Ok(SortDirection::Ascending),
@@ -184,46 +245,21 @@ fn parse_sort_direction(sort_direction: &str) -> Result<SortDirection, Error> {
     }
 }
 
-fn parse_cursor(cursor: &str, value_type: Option<&FieldType>) -> Result<Cursor, Error> {
+fn parse_cursor<T: Model>(cursor: &str, value_type: Option<&FieldType>) -> Result<Cursor, Error> {
     let mut parts = cursor.split('_');
 
     let id = parts
         .next()
         .ok_or_else(|| Error::bad_request("invalid cursor"))?;
 
-    let id: Uuid = from_b64_str(id)?;
+    let id = decode_value(&T::Id::field_type(), id)?;
 
     let value = if let Some(value_type) = value_type {
         let value = parts
             .next()
             .ok_or_else(|| Error::bad_request("invalid cursor: expected value component"))?;
 
-        match value_type {
-            FieldType::Uuid => FieldValue::Uuid(from_b64_str(value)?),
-            FieldType::Bool => FieldValue::Bool(from_b64_str(value)?),
-            FieldType::Int => FieldValue::Int(from_b64_str(value)?),
-            FieldType::Int32 => FieldValue::Int32(from_b64_str(value)?),
-            FieldType::Float => FieldValue::Float(from_b64_str(value)?),
-            FieldType::Decimal => FieldValue::Decimal(from_b64_str(value)?),
-            FieldType::String => FieldValue::String(from_b64_str(value)?),
-            FieldType::Date => FieldValue::Date(from_b64_str(value)?),
-            FieldType::DateTime => FieldValue::DateTime(from_b64_str(value)?),
-            FieldType::Enum(variants) => {
-                let value: String = from_b64_str(value)?;
-
-                variants.iter().find(|&v| v == &value).ok_or_else(|| {
-                    Error::bad_request("invalid cursor value: not a recognized enum variant")
-                })?;
-
-                FieldValue::Enum(value.into())
-            }
-            FieldType::Json => {
-                return Err(Error::bad_request(
-                    "invalid cursor value: json value can't be used as cursor value",
-                ))
-            }
-        }
-        .into()
+        decode_value(value_type, value)?.into()
     } else {
         None
     };
@@ -231,6 +267,36 @@ fn parse_cursor(cursor: &str, value_type: Option<&FieldType>) -> Result<Cursor,
     Ok(Cursor { id, value })
 }
 
+fn decode_value(value_type: &FieldType, value: &str) -> Result<FieldValue, Error> {
+    let value = match value_type {
+        FieldType::Uuid => FieldValue::Uuid(from_b64_str(value)?),
+        FieldType::Bool => FieldValue::Bool(from_b64_str(value)?),
+        FieldType::Int => FieldValue::Int(from_b64_str(value)?),
+        FieldType::Int32 => FieldValue::Int32(from_b64_str(value)?),
+        FieldType::Float => FieldValue::Float(from_b64_str(value)?),
+        FieldType::Decimal => FieldValue::Decimal(from_b64_str(value)?),
+        FieldType::String => FieldValue::String(from_b64_str(value)?),
+        FieldType::Date => FieldValue::Date(from_b64_str(value)?),
+        FieldType::DateTime => FieldValue::DateTime(from_b64_str(value)?),
+        FieldType::Enum(variants) => {
+            let value: String = from_b64_str(value)?;
+
+            variants.iter().find(|&v| v == &value).ok_or_else(|| {
+                Error::bad_request("invalid cursor value: not a recognized enum variant")
+            })?;
+
+            FieldValue::Enum(value.into())
+        }
+        FieldType::Json => {
+            return Err(Error::bad_request(
+                "invalid cursor value: json value can't be used as cursor value",
+            ))
+        }
+    };
+
+    Ok(value)
+}
+
 #[cfg(test)]
 mod test {
     use crate as model;
@@ -256,7 +322,7 @@ mod test {
     fn test_deserialize() {
         let cursor = Cursor {
             value: FieldValue::String("John".to_string().into()).into(),
-            id: Uuid::new_v4(),
+            id: Uuid::new_v4().into(),
         };
 
         let cursor_json = serde_json::to_value(&cursor).unwrap();
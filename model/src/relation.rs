@@ -83,17 +83,35 @@ impl RelationDef {
             format!("{}_{}", parent, self.name)
         };
 
+        // deleted rows are joined as if they didn't exist
+        let soft_delete_condition = match self.model_definition.soft_delete_column() {
+            Some(column) => format!(" AND {}.{} IS NULL", alias, column),
+            None => "".into(),
+        };
+
         match &self.reference {
             Reference::From(column) => {
                 format!(
-                    "LEFT JOIN {} AS {} ON {}.{} = {}.{}",
-                    related_table, alias, parent, column, alias, related_id_column
+                    "LEFT JOIN {} AS {} ON {}.{} = {}.{}{}",
+                    related_table,
+                    alias,
+                    parent,
+                    column,
+                    alias,
+                    related_id_column,
+                    soft_delete_condition
                 )
             }
             Reference::To(column) => {
                 format!(
-                    "LEFT JOIN {} AS {} ON {}.{} = {}.{}",
-                    related_table, alias, parent, parent_id_column, alias, column,
+                    "LEFT JOIN {} AS {} ON {}.{} = {}.{}{}",
+                    related_table,
+                    alias,
+                    parent,
+                    parent_id_column,
+                    alias,
+                    column,
+                    soft_delete_condition
                 )
             }
             Reference::Via((junction_table, from_reference, to_reference)) => {
@@ -110,7 +128,7 @@ impl RelationDef {
                 );
 
                 let join_relation = format!(
-                    "LEFT JOIN {} AS {} ON {}_{}.{} = {}.{}",
+                    "LEFT JOIN {} AS {} ON {}_{}.{} = {}.{}{}",
                     related_table,
                     alias,
                     parent,
@@ -118,6 +136,7 @@ impl RelationDef {
                     to_reference,
                     alias,
                     related_id_column,
+                    soft_delete_condition
                 );
 
                 format!(
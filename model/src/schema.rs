@@ -1,5 +1,6 @@
 use std::collections::HashSet;
 
+use crate::id::UUID_V7_SQL;
 use crate::index::IndexType;
 use crate::relation::Reference;
 use crate::Error;
@@ -200,6 +201,7 @@ pub fn generate_index_schema(entities: &Vec<DDLEntity>) -> String {
 
 fn create_table<T: Model>() -> Result<DDLEntity, Error> {
     let table_name = T::table_name();
+    let id_field_name = T::id_field_name();
     let field_definitions = T::field_definitions();
 
     let columns = field_definitions
@@ -207,6 +209,27 @@ fn create_table<T: Model>() -> Result<DDLEntity, Error> {
         .map(|def| {
             let mut col = format!("{} {}", def.name, def.type_.sql_type());
 
+            let implicit_default = match def.type_ {
+                FieldType::DateTime => "now()",
+                _ => "0",
+            };
+
+            match &def.default {
+                Some(default) => col = format!("{} DEFAULT {}", col, default),
+                None if def.version || def.created_at || def.updated_at => {
+                    col = format!("{} DEFAULT {}", col, implicit_default)
+                }
+                // rows inserted without an id get a time-ordered one, like `new_uuid` generates
+                None if def.name == id_field_name && matches!(def.type_, FieldType::Uuid) => {
+                    col = format!("{} DEFAULT {}", col, UUID_V7_SQL)
+                }
+                None => {}
+            }
+
+            if let Some(generated) = &def.generated {
+                col = format!("{} GENERATED ALWAYS AS ({}) STORED", col, generated);
+            }
+
             if !def.nullable {
                 col = format!("{} {}", col, "NOT NULL");
             }
@@ -248,7 +271,14 @@ fn create_junction_tables_and_foreign_keys<T: Model>() -> Result<Vec<DDLEntity>,
                 let to_table = (def.model_definition.table_name)();
                 let to_table_id_field = (def.model_definition.id_field_name)();
 
-                let columns = format!("{} UUID NOT NULL, {} UUID NOT NULL", from_ref, to_ref);
+                // the references take the type of the id they point to
+                let columns = format!(
+                    "{} {} NOT NULL, {} {} NOT NULL",
+                    from_ref,
+                    T::definition().id_sql_type()?,
+                    to_ref,
+                    def.model_definition.id_sql_type()?
+                );
                 let primary_key = format!("PRIMARY KEY ({}, {})", from_ref, to_ref);
                 let from_foreign_key_constraint = format!(
                     "CONSTRAINT fk_from_reference FOREIGN KEY ({}) REFERENCES {} ({})",
@@ -321,6 +351,10 @@ fn create_indices<T: Model>() -> Result<Vec<DDLEntity>, Error> {
             ));
         }
 
+        if def.unique && !matches!(def.type_, IndexType::BTree) {
+            return Err(Error::bad_request("a fulltext index can't be unique"));
+        }
+
         for column in &def.columns {
             let fdef = field_defs
                 .iter()
@@ -359,9 +393,31 @@ fn create_indices<T: Model>() -> Result<Vec<DDLEntity>, Error> {
                 .join(", "),
         };
 
+        let unique = if def.unique { "UNIQUE " } else { "" };
+
+        let statement = format!(
+            "CREATE {}INDEX {} ON {} USING {} ({});",
+            unique, idx_name, table_name, index_type, columns_string,
+        );
+
+        ddls.push(DDLEntity::Index((idx_name, statement)));
+    }
+
+    // selects exclude deleted rows by default, so lookups of the remaining rows get their own index
+    if let Some(column) = T::definition().soft_delete_column() {
+        let table_name = T::table_name();
+        let idx_name = format!("idx_{}_not_deleted", table_name);
+
+        let primary_key = T::field_definitions()
+            .into_iter()
+            .filter(|def| def.primary_key)
+            .map(|def| def.name)
+            .collect::<Vec<_>>()
+            .join(", ");
+
         let statement = format!(
-            "CREATE INDEX {} ON {} USING {} ({});",
-            idx_name, table_name, index_type, columns_string,
+            "CREATE INDEX {} ON {} ({}) WHERE {} IS NULL;",
+            idx_name, table_name, primary_key, column
         );
 
         ddls.push(DDLEntity::Index((idx_name, statement)));
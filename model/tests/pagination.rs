@@ -1,5 +1,6 @@
 use std::{fs::File, io::BufReader, path::Path, str::FromStr};
 
+use futures::TryStreamExt;
 use model::{schema, Crud, Cursor, Filter, Model, Query, Sort};
 use serde::{Deserialize, Serialize};
 use sqlx::{prelude::FromRow, PgConnection, PgPool, Postgres, Transaction};
@@ -67,6 +68,7 @@ async fn test_pagination() {
         .into();
     query.sort = Sort {
         field: "age".into(),
+        aggregate: None,
         direction: model::SortDirection::Ascending,
     }
     .into();
@@ -82,7 +84,9 @@ async fn test_pagination() {
     assert_eq!(
         connection.page_info.next_cursor,
         Some(Cursor {
-            id: Uuid::from_str("069f0ae7-e354-4099-83c7-4c20616f8d95").unwrap(),
+            id: Uuid::from_str("069f0ae7-e354-4099-83c7-4c20616f8d95")
+                .unwrap()
+                .into(),
             value: Some(model::FieldValue::Int(30.into())),
         })
     );
@@ -108,14 +112,18 @@ async fn test_pagination() {
     assert_eq!(
         connection.page_info.prev_cursor,
         Some(Cursor {
-            id: Uuid::from_str("069f0ae7-e354-4099-83c7-4c20616f8d98").unwrap(),
+            id: Uuid::from_str("069f0ae7-e354-4099-83c7-4c20616f8d98")
+                .unwrap()
+                .into(),
             value: Some(model::FieldValue::Int(28.into())),
         })
     );
     assert_eq!(
         connection.page_info.next_cursor,
         Some(Cursor {
-            id: Uuid::from_str("069f0ae7-e354-4099-83c7-4c20616f8d97").unwrap(),
+            id: Uuid::from_str("069f0ae7-e354-4099-83c7-4c20616f8d97")
+                .unwrap()
+                .into(),
             value: Some(model::FieldValue::Int(31.into())),
         })
     );
@@ -141,7 +149,9 @@ async fn test_pagination() {
     assert_eq!(
         connection.page_info.prev_cursor,
         Some(Cursor {
-            id: Uuid::from_str("069f0ae7-e354-4099-83c7-4c20616f8d95").unwrap(),
+            id: Uuid::from_str("069f0ae7-e354-4099-83c7-4c20616f8d95")
+                .unwrap()
+                .into(),
             value: Some(model::FieldValue::Int(30.into())),
         })
     );
@@ -158,3 +168,345 @@ async fn test_pagination() {
 
     tx.rollback().await.unwrap();
 }
+
+#[tokio::test]
+async fn test_fetch_stream() {
+    let pool = create_db_pool().await;
+
+    let mut tx = pool.begin().await.unwrap();
+
+    setup_tables(&mut tx).await;
+    insert_records(&mut tx).await;
+
+    let streamed: Vec<Dummy> = Dummy::select()
+        .with_filter(Filter::new().field("age").gte(28))
+        .fetch_stream(&mut tx)
+        .try_collect()
+        .await
+        .unwrap();
+
+    let fetched = Dummy::select()
+        .with_filter(Filter::new().field("age").gte(28))
+        .fetch_all(&mut tx)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        streamed.iter().map(|d| d.id).collect::<Vec<_>>(),
+        fetched.iter().map(|d| d.id).collect::<Vec<_>>()
+    );
+
+    tx.rollback().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_batches() {
+    let pool = create_db_pool().await;
+
+    let mut tx = pool.begin().await.unwrap();
+
+    setup_tables(&mut tx).await;
+    insert_records(&mut tx).await;
+
+    let mut batches = Dummy::select().batches(5);
+
+    let mut visited = vec![];
+    let mut inserted = false;
+
+    while let Some(batch) = batches.next(&mut tx).await.unwrap() {
+        assert!(batch.len() <= 5);
+
+        visited.extend(batch.into_iter().map(|d| d.id));
+
+        // rows inserted behind the cursor are not visited, rows ahead of it are
+        if !inserted {
+            Dummy {
+                id: Uuid::nil(),
+                name: None,
+                age: None,
+            }
+            .create()
+            .execute(&mut tx)
+            .await
+            .unwrap();
+
+            Dummy {
+                id: Uuid::max(),
+                name: None,
+                age: None,
+            }
+            .create()
+            .execute(&mut tx)
+            .await
+            .unwrap();
+
+            inserted = true;
+        }
+    }
+
+    let mut expected = read_records().into_iter().map(|d| d.id).collect::<Vec<_>>();
+    expected.sort();
+    expected.push(Uuid::max());
+
+    assert_eq!(visited, expected);
+
+    tx.rollback().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_offset_pagination() {
+    let pool = create_db_pool().await;
+
+    let mut tx = pool.begin().await.unwrap();
+
+    setup_tables(&mut tx).await;
+    insert_records(&mut tx).await;
+
+    let records = read_records();
+    let total = records.len() as i64;
+
+    let mut expected = records.into_iter().map(|d| d.id).collect::<Vec<_>>();
+    expected.sort();
+
+    let query: Query<Dummy> = serde_json::from_value(serde_json::json!({
+        "page": 2,
+        "per_page": 5,
+    }))
+    .unwrap();
+
+    let connection = Dummy::select()
+        .from_query(query)
+        .unwrap()
+        .fetch_page(&mut tx)
+        .await
+        .unwrap();
+
+    let ids = connection.nodes.iter().map(|d| d.id).collect::<Vec<_>>();
+
+    assert_eq!(ids, expected[5..10]);
+    assert_eq!(connection.page_info.page, Some(2));
+    assert_eq!(connection.page_info.per_page, Some(5));
+    assert_eq!(connection.page_info.total_count, Some(total));
+    assert_eq!(connection.page_info.total_pages, Some((total + 4) / 5));
+    assert_eq!(connection.page_info.next_cursor, None);
+
+    // pages past the end are empty but still report the total
+    let connection = Dummy::select()
+        .page(total + 1, 5)
+        .fetch_page(&mut tx)
+        .await
+        .unwrap();
+
+    assert!(connection.nodes.is_empty());
+    assert_eq!(connection.page_info.total_count, Some(total));
+
+    let result = Dummy::select()
+        .offset(20)
+        .max_offset(10)
+        .fetch_page(&mut tx)
+        .await;
+
+    assert!(result.is_err());
+
+    // every rendered query enforces the max offset, not just fetch_page
+    assert!(Dummy::select().offset(20).max_offset(10).to_sql().is_err());
+    assert!(Dummy::select().page(4, 5).max_offset(10).to_sql().is_err());
+    assert!(Dummy::select()
+        .offset(20)
+        .max_offset(10)
+        .fetch_all(&mut tx)
+        .await
+        .is_err());
+    assert!(Dummy::select().page(2, 5).max_offset(10).to_sql().is_ok());
+
+    let query = serde_json::from_value::<Query<Dummy>>(serde_json::json!({
+        "page": 1,
+        "offset": 5,
+    }));
+
+    assert!(query.is_err());
+
+    // page numbers beyond any representable offset are rejected instead of overflowing
+    assert!(Dummy::select().page(i64::MAX, 5).to_sql().is_err());
+    assert!(Dummy::select()
+        .page(1, 0)
+        .fetch_page(&mut tx)
+        .await
+        .is_err());
+
+    let query = serde_json::from_value::<Query<Dummy>>(serde_json::json!({
+        "page": i64::MAX,
+        "per_page": 100,
+    }))
+    .unwrap();
+
+    assert!(Dummy::select()
+        .from_query(query)
+        .unwrap()
+        .fetch_page(&mut tx)
+        .await
+        .is_err());
+
+    let query = serde_json::from_value::<Query<Dummy>>(serde_json::json!({
+        "page": 1,
+        "per_page": 0,
+    }));
+
+    assert!(query.is_err());
+
+    tx.rollback().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_edges() {
+    let pool = create_db_pool().await;
+
+    let mut tx = pool.begin().await.unwrap();
+
+    setup_tables(&mut tx).await;
+    insert_records(&mut tx).await;
+
+    let mut query = Query::<Dummy>::new();
+
+    query.sort = Sort {
+        field: "age".into(),
+        aggregate: None,
+        direction: model::SortDirection::Ascending,
+    }
+    .into();
+    query.limit = 3.into();
+
+    let connection = Dummy::select()
+        .from_query(query.clone())
+        .unwrap()
+        .fetch_edges(&mut tx)
+        .await
+        .unwrap();
+
+    assert_eq!(connection.edges.len(), 3);
+
+    let connection_next_cursor = connection.page_info.next_cursor.clone();
+
+    // resuming from any edge starts the page at that edge's node
+    let edge = connection.edges[1].clone();
+
+    query.cursor = edge.cursor.clone().into();
+
+    let resumed = Dummy::select()
+        .from_query(query)
+        .unwrap()
+        .fetch_edges(&mut tx)
+        .await
+        .unwrap();
+
+    assert_eq!(resumed.edges[0].node.id, edge.node.id);
+    assert_eq!(resumed.edges[0].cursor, edge.cursor);
+
+    let names = connection.map(|d| d.name);
+
+    assert_eq!(names.edges[1].node, edge.node.name);
+    assert_eq!(names.edges[1].cursor, edge.cursor);
+
+    let json = serde_json::to_value(&names).unwrap();
+
+    assert_eq!(json["edges"][1]["node"], serde_json::json!(edge.node.name));
+    assert_eq!(
+        json["edges"][1]["cursor"],
+        serde_json::to_value(&edge.cursor).unwrap()
+    );
+
+    // edges convert to a plain connection, keeping the page cursors
+    let plain = model::Connection::from(names);
+
+    assert_eq!(plain.nodes[1], edge.node.name);
+    assert_eq!(plain.page_info.next_cursor, connection_next_cursor);
+
+    tx.rollback().await.unwrap();
+}
+
+#[derive(Debug, FromRow)]
+struct DummyName {
+    name: Option<String>,
+}
+
+#[tokio::test]
+async fn test_columns() {
+    let pool = create_db_pool().await;
+
+    let mut tx = pool.begin().await.unwrap();
+
+    setup_tables(&mut tx).await;
+    insert_records(&mut tx).await;
+
+    let mut query = Query::<Dummy>::new();
+
+    query.sort = Sort {
+        field: "age".into(),
+        aggregate: None,
+        direction: model::SortDirection::Ascending,
+    }
+    .into();
+    query.limit = 3.into();
+
+    let full = Dummy::select()
+        .from_query(query.clone())
+        .unwrap()
+        .fetch_page(&mut tx)
+        .await
+        .unwrap();
+
+    let projected = Dummy::select()
+        .from_query(query.clone())
+        .unwrap()
+        .columns(&["name"])
+        .fetch_page_as::<DummyName>(&mut tx)
+        .await
+        .unwrap();
+
+    let names = projected
+        .nodes
+        .iter()
+        .map(|d| d.name.clone())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        names,
+        full.nodes
+            .iter()
+            .map(|d| d.name.clone())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(projected.page_info.next_cursor, full.page_info.next_cursor);
+
+    // the cursor of a projected page resumes pagination as usual
+    query.cursor = projected.page_info.next_cursor.clone();
+
+    let next = Dummy::select()
+        .from_query(query)
+        .unwrap()
+        .columns(&["id", "age"])
+        .fetch_page_values(&mut tx)
+        .await
+        .unwrap();
+
+    let first = next.nodes.first().unwrap();
+
+    assert_eq!(first.len(), 2);
+    assert_eq!(
+        Some(&first["id"]),
+        projected.page_info.next_cursor.as_ref().map(|c| &c.id)
+    );
+    assert_eq!(
+        first["age"],
+        projected.page_info.next_cursor.unwrap().value.unwrap()
+    );
+
+    let result = Dummy::select()
+        .columns(&["height"])
+        .fetch_page_values(&mut tx)
+        .await;
+
+    assert!(result.is_err());
+
+    tx.rollback().await.unwrap();
+}
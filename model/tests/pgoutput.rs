@@ -17,7 +17,15 @@ mod models {
         balance: Decimal,
     }
 
-    model::model_wrapper!(Test);
+    #[derive(Serialize, Deserialize, Debug, Clone, model::Model)]
+    #[model(table_name = "serial_test")]
+    pub struct SerialTest {
+        #[model(id, primary_key)]
+        pub id: i64,
+        pub name: String,
+    }
+
+    model::model_wrapper!(Test, SerialTest);
 }
 
 #[test]
@@ -45,3 +53,19 @@ fn test_pgoutput_deserialize() {
     ModelWrapper::from_pgoutput(table_name, &col_names, row)
         .expect("failed to deserialize from pgoutput!");
 }
+
+#[test]
+fn test_pgoutput_deserialize_integer_id() {
+    use models::ModelWrapper;
+
+    let col_names = vec!["id".to_string(), "name".to_string()];
+    let row = vec![Some("42".to_string()), Some("answer".to_string())];
+
+    let wrapper = ModelWrapper::from_pgoutput("serial_test", &col_names, row)
+        .expect("failed to deserialize from pgoutput!");
+
+    match wrapper {
+        ModelWrapper::SerialTest(record) => assert_eq!(record.id, 42),
+        _ => panic!("expected a SerialTest"),
+    }
+}
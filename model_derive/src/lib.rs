@@ -11,15 +11,24 @@ pub fn model_derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
     let ident = input.ident;
+    let vis = input.vis;
 
     let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
 
     let mut has_relations = false;
     let mut has_indices = false;
+    let mut patch = false;
 
     // Initialize the table name to a default or error message in case attribute is not found
     let mut table_name = None;
 
+    // the patch is deserialized like the model, so it takes over the serde renames
+    let patch_attrs = input
+        .attrs
+        .iter()
+        .filter_map(|attr| serde_attribute(attr, &["rename", "rename_all", "deny_unknown_fields"]))
+        .collect::<Vec<_>>();
+
     // Iterate over the attributes to find `model` and then `table_name`
     for attr in input.attrs {
         if let Ok(Meta::List(meta)) = attr.parse_meta() {
@@ -32,6 +41,9 @@ pub fn model_derive(input: TokenStream) -> TokenStream {
                         NestedMeta::Meta(Meta::Path(path)) if path.is_ident("has_indices") => {
                             has_indices = true;
                         }
+                        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("patch") => {
+                            patch = true;
+                        }
                         NestedMeta::Meta(Meta::NameValue(MetaNameValue {
                             path,
                             lit: Lit::Str(lit_str),
@@ -59,20 +71,40 @@ pub fn model_derive(input: TokenStream) -> TokenStream {
     let table_name = table_name.expect("Specify #[model(table_name = \"...\")] attribute");
 
     // find the id field
-    let mut field_attributes = fields.iter().map(|f| (f, parse_attributes(&f.attrs)));
+    let field_attributes = fields.iter().map(|f| (f, parse_attributes(&f.attrs)));
 
     let mut id_field: Option<Ident> = None;
+    let mut id_type: Option<syn::Type> = None;
     let mut primary_key_set = false;
-    while let Some((f, (id, _, _, _, primary_key, _, _))) = field_attributes.next() {
-        if id && id_field.is_some() {
+    let mut version_set = false;
+    let mut soft_delete_set = false;
+    for (f, attributes) in field_attributes {
+        if attributes.soft_delete && soft_delete_set {
+            panic!("only one field may be declared as the soft_delete field")
+        }
+
+        if attributes.soft_delete {
+            soft_delete_set = true;
+        }
+
+        if attributes.version && version_set {
+            panic!("only one field may be declared as the version field")
+        }
+
+        if attributes.version {
+            version_set = true;
+        }
+
+        if attributes.id && id_field.is_some() {
             panic!("only one field may be declared as the id field")
         }
 
-        if id {
+        if attributes.id {
             id_field = f.ident.clone();
+            id_type = f.ty.clone().into();
         }
 
-        if primary_key {
+        if attributes.primary_key {
             primary_key_set = true;
         }
     }
@@ -81,17 +113,35 @@ pub fn model_derive(input: TokenStream) -> TokenStream {
         panic!("at least one field must be set as the primary key");
     }
 
-    let id_field =
-        id_field.expect("at least one field of type Uuid must be declared as the id field");
+    let id_field = id_field.expect("at least one field must be declared as the id field");
+    let id_type = id_type.unwrap();
 
     let id_field_string = id_field.to_string();
 
+    let mut patch_fields = vec![];
+    let mut patch_field_getters = vec![];
+
     let (field_definitions, field_value_getters): (Vec<_>, Vec<_>) = fields
         .iter()
         .filter_map(|f| {
             let name_ident = f.ident.as_ref().unwrap();
             let name = name_ident.to_string();
-            let (id, json, skip, immutable, primary_key, unique, enum_) = parse_attributes(&f.attrs);
+            let FieldAttributes {
+                id,
+                json,
+                skip,
+                immutable,
+                primary_key,
+                unique,
+                enum_,
+                read_only,
+                default,
+                generated,
+                version,
+                created_at,
+                updated_at,
+                soft_delete,
+            } = parse_attributes(&f.attrs);
 
             if id {
                 if skip {
@@ -107,10 +157,53 @@ pub fn model_derive(input: TokenStream) -> TokenStream {
                 return None;
             }
 
+            if generated.is_some() && default.is_some() {
+                panic!("Field {} can't have both a default and be generated", name);
+            }
+
+            // generated columns can't be written to
+            let read_only = read_only || generated.is_some();
+
+            let default = match default {
+                Some(default) => quote! { Some(String::from(#default)) },
+                None => quote! { None },
+            };
+
+            let generated = match generated {
+                Some(generated) => quote! { Some(String::from(#generated)) },
+                None => quote! { None },
+            };
+
             let (field_type, nesting) = map_field_type(&f.ty, id, json, enum_, 0);
 
             let nullable = nesting > 0;
 
+            if version {
+                let type_string = field_type.to_string().replace(' ', "");
+
+                if nullable
+                    || !matches!(
+                        type_string.as_str(),
+                        "model::FieldType::Int" | "model::FieldType::Int32" | "model::FieldType::DateTime"
+                    )
+                {
+                    panic!("Field declared as version must be of type i64, i32 or DateTime and not optional");
+                }
+            }
+
+            if (created_at || updated_at)
+                && field_type.to_string().replace(' ', "") != "model::FieldType::DateTime"
+            {
+                panic!("Field declared as created_at or updated_at must be of type DateTime");
+            }
+
+            if soft_delete
+                && (!nullable
+                    || field_type.to_string().replace(' ', "") != "model::FieldType::DateTime")
+            {
+                panic!("Field declared as soft_delete must be of type Option<DateTime>");
+            }
+
             let field_definition = quote! {
                 model::FieldDefinition {
                     name: String::from(#name),
@@ -119,6 +212,13 @@ pub fn model_derive(input: TokenStream) -> TokenStream {
                     primary_key: #primary_key,
                     unique: #unique,
                     nullable: #nullable,
+                    read_only: #read_only,
+                    default: #default,
+                    generated: #generated,
+                    version: #version,
+                    created_at: #created_at,
+                    updated_at: #updated_at,
+                    soft_delete: #soft_delete,
                 }
             };
 
@@ -154,12 +254,51 @@ pub fn model_derive(input: TokenStream) -> TokenStream {
                 },
             };
 
+            // nullable fields are wrapped twice in the patch, so that null can be set explicitly
+            let ty = &f.ty;
+            let field_attrs = f
+                .attrs
+                .iter()
+                .filter_map(|attr| serde_attribute(attr, &["rename", "alias"]));
+
+            let patch_field = if nullable {
+                quote! {
+                    #(#field_attrs)*
+                    #[serde(default, deserialize_with = "model::deserialize_some")]
+                    pub #name_ident: Option<#ty>
+                }
+            } else {
+                quote! {
+                    #(#field_attrs)*
+                    #[serde(default)]
+                    pub #name_ident: Option<#ty>
+                }
+            };
+
+            let patch_field_getter = quote! {
+                if let Some(value) = &self.#name_ident {
+                    let value = value.clone();
+
+                    let field_value = (|| -> Result<model::FieldValue, model::Error> {
+                        #value_as_option
+                        #return_result_field_value
+                    })()?;
+
+                    fields.push((String::from(#name), field_value));
+                }
+            };
+
+            patch_fields.push(patch_field);
+            patch_field_getters.push(patch_field_getter);
+
             Some((field_definition, field_value_getter))
         })
         .unzip();
 
     let model_impl = quote! {
         impl #impl_generics model::Model for #ident #type_generics #where_clause {
+            type Id = #id_type;
+
             fn table_name() -> String {
                 #table_name.into()
             }
@@ -172,7 +311,7 @@ pub fn model_derive(input: TokenStream) -> TokenStream {
                 vec![#(#field_definitions),*]
             }
 
-            fn id_field_value(&self) -> Uuid {
+            fn id_field_value(&self) -> Self::Id {
                 self.#id_field.clone()
             }
 
@@ -185,6 +324,38 @@ pub fn model_derive(input: TokenStream) -> TokenStream {
         }
     };
 
+    let patch_impl = if patch {
+        // patches of generic models aren't supported, since the type parameters may not be used by every field
+        if !input.generics.params.is_empty() {
+            panic!("patch can't be derived for generic models");
+        }
+
+        let patch_ident = Ident::new(&format!("{}Patch", ident), ident.span());
+
+        quote! {
+            #[derive(Clone, Debug, Default, model::serde::Deserialize)]
+            #[serde(crate = "model::serde")]
+            #(#patch_attrs)*
+            #vis struct #patch_ident {
+                #(#patch_fields),*
+            }
+
+            impl model::ModelPatch for #patch_ident {
+                type Model = #ident;
+
+                fn fields(&self) -> Result<Vec<(String, model::FieldValue)>, model::Error> {
+                    let mut fields = vec![];
+
+                    #(#patch_field_getters)*
+
+                    Ok(fields)
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+
     let related_impl = if has_relations {
         quote! {}
     } else {
@@ -204,6 +375,8 @@ pub fn model_derive(input: TokenStream) -> TokenStream {
     let out = quote! {
         #model_impl
 
+        #patch_impl
+
         #related_impl
 
         #indexed_impl
@@ -212,6 +385,38 @@ pub fn model_derive(input: TokenStream) -> TokenStream {
     out.into()
 }
 
+/// Keeps the given options of a `#[serde(...)]` attribute, dropping the attribute if none is left
+fn serde_attribute(attr: &Attribute, options: &[&str]) -> Option<proc_macro2::TokenStream> {
+    let Ok(Meta::List(meta)) = attr.parse_meta() else {
+        return None;
+    };
+
+    if !meta.path.is_ident("serde") {
+        return None;
+    }
+
+    let kept = meta
+        .nested
+        .into_iter()
+        .filter(|nested| {
+            let path = match nested {
+                NestedMeta::Meta(Meta::Path(path)) => path,
+                NestedMeta::Meta(Meta::List(list)) => &list.path,
+                NestedMeta::Meta(Meta::NameValue(name_value)) => &name_value.path,
+                NestedMeta::Lit(_) => return false,
+            };
+
+            options.iter().any(|option| path.is_ident(option))
+        })
+        .collect::<Vec<_>>();
+
+    if kept.is_empty() {
+        return None;
+    }
+
+    Some(quote! { #[serde(#(#kept),*)] })
+}
+
 fn flatten_option(mut nesting: usize) -> proc_macro2::TokenStream {
     let mut accumulated = quote!();
 
@@ -243,11 +448,11 @@ fn map_field_type(
             let ident_str = ident.to_string();
 
             if id {
-                if ident_str != "Uuid" {
-                    panic!("Field declared as id must be of type Uuid")
+                if !matches!(ident_str.as_str(), "Uuid" | "i64" | "i32" | "String") {
+                    panic!("Field declared as id must be of type Uuid, i64, i32 or String")
                 }
 
-                return (quote!(model::FieldType::Uuid), level);
+                return (map_field_inner_type(ident, false), level);
             }
 
             if ident_str == "Option" {
@@ -297,14 +502,26 @@ fn map_field_inner_type(inner_type: &Ident, enum_: bool) -> proc_macro2::TokenSt
     }
 }
 
-fn parse_attributes(attrs: &[Attribute]) -> (bool, bool, bool, bool, bool, bool, bool) {
-    let mut id = false;
-    let mut json = false;
-    let mut skip = false;
-    let mut immutable = false;
-    let mut primary_key = false;
-    let mut unique = false;
-    let mut enum_ = false;
+#[derive(Default)]
+struct FieldAttributes {
+    id: bool,
+    json: bool,
+    skip: bool,
+    immutable: bool,
+    primary_key: bool,
+    unique: bool,
+    enum_: bool,
+    read_only: bool,
+    default: Option<String>,
+    generated: Option<String>,
+    version: bool,
+    created_at: bool,
+    updated_at: bool,
+    soft_delete: bool,
+}
+
+fn parse_attributes(attrs: &[Attribute]) -> FieldAttributes {
+    let mut attributes = FieldAttributes::default();
 
     for attr in attrs {
         if let Ok(Meta::List(meta)) = attr.parse_meta() {
@@ -312,27 +529,55 @@ fn parse_attributes(attrs: &[Attribute]) -> (bool, bool, bool, bool, bool, bool,
                 for nested in meta.nested {
                     match nested {
                         syn::NestedMeta::Meta(Meta::Path(path)) if path.is_ident("id") => {
-                            id = true;
-                            unique = true;
-                            immutable = true;
+                            attributes.id = true;
+                            attributes.unique = true;
+                            attributes.immutable = true;
                         }
                         syn::NestedMeta::Meta(Meta::Path(path)) if path.is_ident("json") => {
-                            json = true;
+                            attributes.json = true;
                         }
                         syn::NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => {
-                            skip = true;
+                            attributes.skip = true;
                         }
                         syn::NestedMeta::Meta(Meta::Path(path)) if path.is_ident("immutable") => {
-                            immutable = true;
+                            attributes.immutable = true;
                         }
                         syn::NestedMeta::Meta(Meta::Path(path)) if path.is_ident("primary_key") => {
-                            primary_key = true;
+                            attributes.primary_key = true;
                         }
                         syn::NestedMeta::Meta(Meta::Path(path)) if path.is_ident("unique") => {
-                            unique = true;
+                            attributes.unique = true;
                         }
                         syn::NestedMeta::Meta(Meta::Path(path)) if path.is_ident("enum") => {
-                            enum_ = true;
+                            attributes.enum_ = true;
+                        }
+                        syn::NestedMeta::Meta(Meta::Path(path)) if path.is_ident("created_at") => {
+                            // the creation time is never changed after the insert
+                            attributes.created_at = true;
+                            attributes.immutable = true;
+                        }
+                        syn::NestedMeta::Meta(Meta::Path(path)) if path.is_ident("updated_at") => {
+                            attributes.updated_at = true;
+                        }
+                        syn::NestedMeta::Meta(Meta::Path(path)) if path.is_ident("soft_delete") => {
+                            attributes.soft_delete = true;
+                        }
+                        syn::NestedMeta::Meta(Meta::Path(path)) if path.is_ident("version") => {
+                            attributes.version = true;
+                        }
+                        syn::NestedMeta::Meta(Meta::Path(path)) if path.is_ident("read_only") => {
+                            attributes.read_only = true;
+                        }
+                        syn::NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                            path,
+                            lit: Lit::Str(lit_str),
+                            ..
+                        })) => {
+                            if path.is_ident("default") {
+                                attributes.default = lit_str.value().into();
+                            } else if path.is_ident("generated") {
+                                attributes.generated = lit_str.value().into();
+                            }
                         }
                         _ => {}
                     }
@@ -341,5 +586,5 @@ fn parse_attributes(attrs: &[Attribute]) -> (bool, bool, bool, bool, bool, bool,
         }
     }
 
-    (id, json, skip, immutable, primary_key, unique, enum_)
+    attributes
 }